@@ -1,21 +1,31 @@
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::ops::{Add, Sub};
 
 use robotics_lib::world::coordinates::Coordinate;
+use robotics_lib::world::tile::Tile;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 #[derive(Copy, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// struct: ChartedCoordinate
 ///
 /// it is simply a custom type compatible with robotics_lib::world::coordinates::Coordinate,
 /// implemented for more liberty and ease of use inside this crate
 ///
+/// the first field is the row and the second the column, as in robotics_lib; `x()` and `y()`
+/// give the same values following the screen convention (`x` is the column, `y` the row)
+///
 /// ## Usage:
 ///
 ///     let c = ChartedCoordinate::default();
 ///
 ///     let c = ChartedCoordinate::new(0, 1);
 ///
+///     let c = ChartedCoordinate::from_xy(1, 0); // same as above
+///
 ///     let c = ChartedCoordinate::from(robot.coordinate);
 ///
 ///     let tuple = (0usize, 0usize);
@@ -58,6 +68,16 @@ impl ChartedCoordinate {
         Self(row, col)
     }
 
+    /// same as `new`, but it returns `None` if the coordinate would fall outside of a world of side `dim`,
+    /// so that an out of bounds coordinate is caught when it is created instead of when it is used
+    pub fn new_checked(row: usize, col: usize, dim: usize) -> Option<Self> {
+        if row < dim && col < dim {
+            Some(Self(row, col))
+        } else {
+            None
+        }
+    }
+
     pub fn get_row(&self) -> usize {
         self.0
     }
@@ -66,9 +86,129 @@ impl ChartedCoordinate {
         self.1
     }
 
+    /// creates the coordinate from screen coordinates: `x` grows to the right (it is the column)
+    /// and `y` grows downward (it is the row), so `from_xy(x, y) == new(y, x)`
+    pub fn from_xy(x: usize, y: usize) -> Self {
+        Self(y, x)
+    }
+
+    /// horizontal screen coordinate, growing to the right: the same as `get_col`
+    pub fn x(&self) -> usize {
+        self.1
+    }
+
+    /// vertical screen coordinate, growing downward: the same as `get_row`
+    pub fn y(&self) -> usize {
+        self.0
+    }
+
+    /// converts the coordinates into (row, col) tuples, the form expected by robotics_lib's
+    /// interfaces like `discover_tiles`
+    pub fn to_tuples(coords: &[ChartedCoordinate]) -> Vec<(usize, usize)> {
+        coords.iter().map(|c| (c.0, c.1)).collect()
+    }
+
+    /// the inverse of `to_tuples`: converts (row, col) tuples into coordinates
+    pub fn from_tuples(tuples: &[(usize, usize)]) -> Vec<ChartedCoordinate> {
+        tuples.iter().map(|t| ChartedCoordinate::from(*t)).collect()
+    }
+
+    /// returns where `other` lies with respect to this coordinate, see `Quadrant`.
+    /// only the signs of the differences matter: a coordinate 10 rows up and 1 column right is `NorthEast`
+    pub fn quadrant_of(&self, other: &ChartedCoordinate) -> Quadrant {
+        match (other.0.cmp(&self.0), other.1.cmp(&self.1)) {
+            | (Ordering::Equal, Ordering::Equal) => Quadrant::Here,
+            | (Ordering::Less, Ordering::Equal) => Quadrant::North,
+            | (Ordering::Less, Ordering::Greater) => Quadrant::NorthEast,
+            | (Ordering::Equal, Ordering::Greater) => Quadrant::East,
+            | (Ordering::Greater, Ordering::Greater) => Quadrant::SouthEast,
+            | (Ordering::Greater, Ordering::Equal) => Quadrant::South,
+            | (Ordering::Greater, Ordering::Less) => Quadrant::SouthWest,
+            | (Ordering::Equal, Ordering::Less) => Quadrant::West,
+            | (Ordering::Less, Ordering::Less) => Quadrant::NorthWest,
+        }
+    }
+
+    /// returns the angle, in radians between -π and π, of the direction going from this coordinate to `other`.
+    /// the axes follow the same convention of `quadrant_of`: 0 points east (growing column), π/2 north (toward
+    /// row 0), -π/2 south and π west. The angle between two equal coordinates is 0
+    pub fn angle_to(&self, other: &ChartedCoordinate) -> f64 {
+        if self == other {
+            return 0.0;
+        }
+        let north = self.0 as f64 - other.0 as f64;
+        let east = other.1 as f64 - self.1 as f64;
+        north.atan2(east)
+    }
+
     pub fn distance_to(who: &ChartedCoordinate, to: &ChartedCoordinate) -> (i32, i32) {
         ((who.0 as i32 - to.0 as i32), (who.1 as i32 - to.1 as i32))
     }
+    pub fn manhattan_distance(who: &ChartedCoordinate, to: &ChartedCoordinate) -> usize {
+        who.0.abs_diff(to.0) + who.1.abs_diff(to.1)
+    }
+    /// adds the offset to the coordinate on a toroidal world of side `dim`,
+    /// so that going past the last row or column starts again from 0
+    ///
+    /// panics if `dim` is 0
+    pub fn wrapping_add(&self, offset: (usize, usize), dim: usize) -> ChartedCoordinate {
+        Self((self.0 % dim + offset.0 % dim) % dim, (self.1 % dim + offset.1 % dim) % dim)
+    }
+
+    /// subtracts the offset from the coordinate on a toroidal world of side `dim`,
+    /// so that going before row or column 0 lands on the last one
+    ///
+    /// panics if `dim` is 0
+    pub fn wrapping_sub(&self, offset: (usize, usize), dim: usize) -> ChartedCoordinate {
+        Self((self.0 % dim + dim - offset.0 % dim) % dim, (self.1 % dim + dim - offset.1 % dim) % dim)
+    }
+
+    /// adds the offset to the coordinate, returning None if the result overflows or falls outside
+    /// of a world of side `dim` (ie any of its components is not in `0..dim`)
+    pub fn checked_add_offset(&self, offset: (usize, usize), dim: usize) -> Option<ChartedCoordinate> {
+        let row = self.0.checked_add(offset.0).filter(|r| *r < dim)?;
+        let col = self.1.checked_add(offset.1).filter(|c| *c < dim)?;
+        Some(Self(row, col))
+    }
+
+    /// returns the four orthogonal neighbours (up, down, left, right) on a toroidal world of side `dim`:
+    /// the tile above row 0 is on row `dim - 1` and so on
+    ///
+    /// panics if `dim` is 0
+    pub fn wrapping_neighbors(&self, dim: usize) -> impl Iterator<Item = ChartedCoordinate> {
+        [
+            self.wrapping_sub((1, 0), dim),
+            self.wrapping_add((1, 0), dim),
+            self.wrapping_sub((0, 1), dim),
+            self.wrapping_add((0, 1), dim),
+        ]
+        .into_iter()
+    }
+
+    /// returns the cells crossed by the straight line from `who` to `to`, both included, computed with
+    /// Bresenham's algorithm: consecutive cells are adjacent, diagonals included
+    pub fn bresenham_line(who: &ChartedCoordinate, to: &ChartedCoordinate) -> Vec<ChartedCoordinate> {
+        let (mut r, mut c) = (who.0 as i64, who.1 as i64);
+        let (r1, c1) = (to.0 as i64, to.1 as i64);
+        let (dr, dc) = ((r1 - r).abs(), -(c1 - c).abs());
+        let (sr, sc) = (if r < r1 { 1 } else { -1 }, if c < c1 { 1 } else { -1 });
+        let mut err = dr + dc;
+        let mut line = vec![*who];
+        while (r, c) != (r1, c1) {
+            let e2 = 2 * err;
+            if e2 >= dc {
+                err += dc;
+                r += sr;
+            }
+            if e2 <= dr {
+                err += dr;
+                c += sc;
+            }
+            line.push(Self(r as usize, c as usize));
+        }
+        line
+    }
+
     pub fn is_close_to(who: &ChartedCoordinate, to: &ChartedCoordinate) -> bool {
         return if (ChartedCoordinate::distance_to(who, to).0).pow(2)
             + ((ChartedCoordinate::distance_to(who, to).1).pow(2))
@@ -81,6 +221,121 @@ impl ChartedCoordinate {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// enum: DistanceMetric
+///
+/// straight-line metrics that can be used to measure the distance between two coordinates
+/// - Manhattan: number of orthogonal steps, |dr| + |dc|
+/// - Euclidean: length of the segment between the two, sqrt(dr² + dc²)
+/// - Chebyshev: number of steps if diagonal moves were allowed, max(|dr|, |dc|)
+///
+/// ## Usage:
+///
+///     let d = DistanceMetric::Euclidean.distance(&ChartedCoordinate(0, 0), &ChartedCoordinate(3, 4));
+///     assert_eq!(d, 5.0);
+pub enum DistanceMetric {
+    Manhattan,
+    Euclidean,
+    Chebyshev,
+}
+
+impl DistanceMetric {
+    pub fn distance(&self, a: &ChartedCoordinate, b: &ChartedCoordinate) -> f64 {
+        let (dr, dc) = (a.0.abs_diff(b.0) as f64, a.1.abs_diff(b.1) as f64);
+        match self {
+            | DistanceMetric::Manhattan => dr + dc,
+            | DistanceMetric::Euclidean => (dr * dr + dc * dc).sqrt(),
+            | DistanceMetric::Chebyshev => dr.max(dc),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// enum: Quadrant
+///
+/// rough position of a coordinate with respect to another, as returned by `ChartedCoordinate::quadrant_of`:
+/// north is toward row 0 and west toward column 0, like the `Up` and `Left` directions of robotics_lib.
+/// `Here` means that the two coordinates are the same
+///
+/// ## Usage:
+///
+///     let q = ChartedCoordinate(5, 5).quadrant_of(&ChartedCoordinate(2, 8));
+///     assert_eq!(q, Quadrant::NorthEast);
+pub enum Quadrant {
+    Here,
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+/// returns the coordinates present in both slices, without duplicates and sorted by row, then by column
+pub fn intersection(a: &[ChartedCoordinate], b: &[ChartedCoordinate]) -> Vec<ChartedCoordinate> {
+    let b: HashSet<&ChartedCoordinate> = b.iter().collect();
+    sorted_unique(a.iter().filter(|c| b.contains(c)))
+}
+
+/// returns the coordinates of `a` that are not in `b`, without duplicates and sorted by row, then by column
+pub fn difference(a: &[ChartedCoordinate], b: &[ChartedCoordinate]) -> Vec<ChartedCoordinate> {
+    let b: HashSet<&ChartedCoordinate> = b.iter().collect();
+    sorted_unique(a.iter().filter(|c| !b.contains(c)))
+}
+
+/// returns the coordinates present in at least one of the slices, without duplicates and sorted by row, then by column
+pub fn union(a: &[ChartedCoordinate], b: &[ChartedCoordinate]) -> Vec<ChartedCoordinate> {
+    sorted_unique(a.iter().chain(b.iter()))
+}
+
+/// returns the discovered tile closest to `from` (by manhattan distance) for which the predicate is true,
+/// scanning the map in rings of growing distance around it, or `None` if no tile matches.
+/// on equal distance the smallest coordinate (by row, then by column) wins; `from` itself is checked first
+/// and does not need to be inside the map
+///
+/// ## Usage:
+///
+///     let map = robot_map(world).unwrap();
+///     let water = nearest_matching(&map, my_coordinate, |tile| tile.tile_type == TileType::ShallowWater);
+pub fn nearest_matching(
+    map: &Vec<Vec<Option<Tile>>>,
+    from: ChartedCoordinate,
+    predicate: impl Fn(&Tile) -> bool,
+) -> Option<ChartedCoordinate> {
+    let rows = map.len();
+    let cols = map.iter().map(|row| row.len()).max().unwrap_or(0);
+    if rows == 0 || cols == 0 {
+        return None;
+    }
+    // the farthest cell of the map is at most this far away
+    let max_distance = from.0.max(rows - 1) + from.1.max(cols - 1);
+    for distance in 0..=max_distance {
+        for row in from.0.saturating_sub(distance)..=(from.0 + distance).min(rows - 1) {
+            let rest = distance - row.abs_diff(from.0);
+            let mut cols_at = vec![from.1.checked_sub(rest), Some(from.1 + rest)];
+            if rest == 0 {
+                cols_at.pop();
+            }
+            for col in cols_at.into_iter().flatten() {
+                let tile = map[row].get(col).and_then(|tile| tile.as_ref());
+                if tile.map_or(false, |tile| predicate(tile)) {
+                    return Some(ChartedCoordinate(row, col));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn sorted_unique<'a>(coordinates: impl Iterator<Item = &'a ChartedCoordinate>) -> Vec<ChartedCoordinate> {
+    let mut result: Vec<ChartedCoordinate> = coordinates.copied().collect();
+    result.sort_by_key(|c| (c.0, c.1));
+    result.dedup();
+    result
+}
+
 impl Add for ChartedCoordinate {
     type Output = ChartedCoordinate;
 
@@ -161,4 +416,81 @@ impl PartialOrd<usize> for ChartedCoordinate {
     }
 }
 
-impl Eq for ChartedCoordinate {}
\ No newline at end of file
+impl Eq for ChartedCoordinate {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapping_at_row_and_column_zero() {
+        let dim = 4;
+        let corner = ChartedCoordinate(0, 0);
+        assert_eq!(corner.wrapping_sub((1, 0), dim), ChartedCoordinate(3, 0));
+        assert_eq!(corner.wrapping_sub((0, 1), dim), ChartedCoordinate(0, 3));
+        assert_eq!(corner.wrapping_sub((5, 9), dim), ChartedCoordinate(3, 3));
+        assert_eq!(ChartedCoordinate(3, 3).wrapping_add((1, 1), dim), corner);
+        assert_eq!(ChartedCoordinate(0, 2).wrapping_add((0, 6), dim), corner);
+
+        let neighbors: Vec<ChartedCoordinate> = corner.wrapping_neighbors(dim).collect();
+        // up, down, left and right
+        let expected =
+            vec![ChartedCoordinate(3, 0), ChartedCoordinate(1, 0), ChartedCoordinate(0, 3), ChartedCoordinate(0, 1)];
+        assert_eq!(neighbors, expected);
+        // on a world of side 1 every neighbour is the tile itself
+        assert!(corner.wrapping_neighbors(1).all(|c| c == corner));
+    }
+
+    #[test]
+    fn checked_add_offset_at_the_far_corner() {
+        let dim = 4;
+        let corner = ChartedCoordinate(3, 3);
+        assert_eq!(corner.checked_add_offset((0, 0), dim), Some(corner));
+        assert_eq!(corner.checked_add_offset((1, 0), dim), None);
+        assert_eq!(corner.checked_add_offset((0, 1), dim), None);
+        assert_eq!(ChartedCoordinate(2, 1).checked_add_offset((1, 2), dim), Some(corner));
+        assert_eq!(ChartedCoordinate(usize::MAX, 0).checked_add_offset((1, 0), usize::MAX), None);
+    }
+
+    #[test]
+    fn new_checked_in_and_out_of_range() {
+        assert_eq!(ChartedCoordinate::new_checked(0, 0, 3), Some(ChartedCoordinate(0, 0)));
+        assert_eq!(ChartedCoordinate::new_checked(2, 1, 3), Some(ChartedCoordinate(2, 1)));
+        assert_eq!(ChartedCoordinate::new_checked(3, 1, 3), None);
+        assert_eq!(ChartedCoordinate::new_checked(1, 3, 3), None);
+        assert_eq!(ChartedCoordinate::new_checked(0, 0, 0), None);
+    }
+
+    #[test]
+    fn quadrant_of_every_direction() {
+        let center = ChartedCoordinate(5, 5);
+        let expected = [
+            ((5, 5), Quadrant::Here),
+            ((2, 5), Quadrant::North),
+            ((2, 8), Quadrant::NorthEast),
+            ((5, 6), Quadrant::East),
+            ((9, 9), Quadrant::SouthEast),
+            ((6, 5), Quadrant::South),
+            ((7, 0), Quadrant::SouthWest),
+            ((5, 0), Quadrant::West),
+            ((4, 4), Quadrant::NorthWest),
+        ];
+        for (other, quadrant) in expected {
+            assert_eq!(center.quadrant_of(&ChartedCoordinate::from(other)), quadrant, "{other:?}");
+        }
+    }
+
+    #[test]
+    fn angle_to_cardinal_directions() {
+        use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+        let center = ChartedCoordinate(5, 5);
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(center.angle_to(&ChartedCoordinate(5, 9)), 0.0));
+        assert!(close(center.angle_to(&ChartedCoordinate(0, 5)), FRAC_PI_2));
+        assert!(close(center.angle_to(&ChartedCoordinate(5, 1)), PI));
+        assert!(close(center.angle_to(&ChartedCoordinate(8, 5)), -FRAC_PI_2));
+        // north east, toward row 0
+        assert!(close(center.angle_to(&ChartedCoordinate(3, 7)), FRAC_PI_4));
+        assert_eq!(center.angle_to(&center), 0.0);
+    }
+}
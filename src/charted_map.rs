@@ -4,10 +4,11 @@ use std::fmt::{Debug, Display, Formatter};
 use std::hash::Hash;
 use std::ops::Range;
 
+use petgraph::unionfind::UnionFind;
 use robotics_lib::world::tile::{Content, Tile, TileType};
 
-use crate::charted_coordinate::ChartedCoordinate;
-use crate::{reserved::New, ChartingTool, NUMBER};
+use crate::charted_coordinate::{ChartedCoordinate, DistanceMetric};
+use crate::{reserved::New, ChartingTool, Slot};
 
 /// # Trait: MapKey
 /// it is an internal trait that defines what can be used as a generic for ChartedMap
@@ -16,8 +17,14 @@ use crate::{reserved::New, ChartingTool, NUMBER};
 /// -       robotics_lib::world::tile::Content
 /// -       robotics_lib::world::tile::TileType
 /// -       robotics_lib::world::tile::Tile
+///
+/// two points of interest are the "same key" for a ChartedMap when their `to_default` forms are equal:
+/// the map is always bucketed, read and written through that form, never through the value passed by the user,
+/// while what `to_default` drops is kept as the SavedQuantity of the entry
 pub trait MapKey: Clone + Debug + Hash + Eq + PartialEq {
-    /// returns a default value for the relevant datatype
+    /// returns a default value for the relevant datatype.
+    /// It must be idempotent (`x.to_default().to_default() == x.to_default()`), since it is the form
+    /// that gets hashed and compared by the map
     fn to_default(&self) -> Self;
     /// returns a quantity for the relevant datatype
     fn get_quantity(&self) -> SavedQuantity;
@@ -25,6 +32,9 @@ pub trait MapKey: Clone + Debug + Hash + Eq + PartialEq {
     fn from(tile: &Tile) -> Self;
 }
 
+/// two tiles are the same key when they have the same tile type (teleports are told apart
+/// by their activation) and the same kind of content, whatever its quantity; the elevation is ignored
+/// and saved as `SavedQuantity::TileElevation`
 impl MapKey for Tile {
     fn to_default(&self) -> Self {
         Tile {
@@ -43,8 +53,11 @@ impl MapKey for Tile {
     }
 }
 
+/// two contents are the same key when they are the same variant, whatever the quantity or range they hold,
+/// which is saved as `SavedQuantity::ContentQuantity` or `SavedQuantity::ContentRange`
 impl MapKey for Content {
     fn to_default(&self) -> Self {
+        // resolves to robotics_lib's inherent Content::to_default, not to this trait method
         self.to_default()
     }
 
@@ -69,6 +82,7 @@ impl MapKey for Content {
     }
 }
 
+/// two tile types are the same key only when they are equal, nothing is dropped
 impl MapKey for TileType {
     fn to_default(&self) -> Self {
         self.clone()
@@ -157,6 +171,23 @@ impl SavedQuantity {
             | _ => false,
         }
     }
+
+    /// returns the saved value as a number:
+    /// - the quantity for `SavedQuantity::ContentQuantity(_)` and `SavedQuantity::TileElevation(_)`
+    /// - the length of the range for `SavedQuantity::ContentRange(_)`
+    /// - None for `SavedQuantity::None`
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            | SavedQuantity::None => None,
+            | SavedQuantity::ContentQuantity(q) | SavedQuantity::TileElevation(q) => Some(*q),
+            | SavedQuantity::ContentRange(r) => Some(r.len()),
+        }
+    }
+
+    // numeric value used to compare quantities, ranges count as their length
+    fn value(&self) -> usize {
+        self.as_usize().unwrap_or(0)
+    }
 }
 
 impl PartialEq for SavedQuantity {
@@ -221,15 +252,13 @@ impl Display for SavedQuantity {
 #[derive(Debug, Clone, PartialEq)]
 pub struct ChartedMap<K: MapKey> {
     map: HashMap<K, Vec<(ChartedCoordinate, SavedQuantity)>>,
+    ticks: HashMap<(K, ChartedCoordinate), u64>,
+    slot: Slot,
 }
 
 impl<K: MapKey> Drop for ChartedMap<K> {
     fn drop(&mut self) {
-        if let Ok(mut n) = NUMBER.lock() {
-            if *n > 0 {
-                *n = *n - 1;
-            }
-        }
+        self.slot.release();
     }
 }
 
@@ -237,11 +266,37 @@ impl<K: MapKey> ChartingTool for ChartedMap<K> {}
 
 impl<K: MapKey> New for ChartedMap<K> {
     fn new() -> Self {
-        Self { map: HashMap::new() }
+        Self { map: HashMap::new(), ticks: HashMap::new(), slot: Slot::acquired() }
     }
 }
 
 impl<K: MapKey> ChartedMap<K> {
+    /// creates a ChartedMap already populated, saving every entry in order as `save` would.
+    /// handy for tests and seeded scenarios, where allocating the tool and saving in a loop is just noise
+    ///
+    /// **NB**: it does not go through `ChartingTools::tool`, so it takes no slot among the active tools
+    /// and the limit does not apply to it: like a clone, it is plain in-memory data
+    ///
+    /// ## Example
+    /// ```
+    /// use robotics_lib::world::tile::Content;
+    /// use charting_tools::charted_coordinate::ChartedCoordinate;
+    /// use charting_tools::charted_map::ChartedMap;
+    ///
+    /// let cm = ChartedMap::from_entries(vec![
+    ///     (Content::Tree(2), ChartedCoordinate(0, 1)),
+    ///     (Content::Rock(1), ChartedCoordinate(3, 4)),
+    /// ]);
+    /// assert_eq!(cm.get(&Content::Tree(0)).unwrap().len(), 1);
+    /// ```
+    pub fn from_entries<I: IntoIterator<Item = (K, ChartedCoordinate)>>(entries: I) -> Self {
+        let mut map = Self { map: HashMap::new(), ticks: HashMap::new(), slot: Slot::uncounted() };
+        for (poi, coordinate) in entries {
+            map.save(&poi, &coordinate);
+        }
+        map
+    }
+
     pub fn copy(&mut self, value: Vec<Vec<Option<Tile>>>) {
         self.clear();
         for (i, row) in value.iter().enumerate() {
@@ -256,9 +311,52 @@ impl<K: MapKey> ChartedMap<K> {
     pub fn iter(&self) -> Iter<'_, K, Vec<(ChartedCoordinate, SavedQuantity)>> {
         self.map.iter()
     }
+    /// same as `iter`, but the points of interest are always in the same order, whatever the run and the saving order.
+    ///
+    /// the keys have no natural ordering, so they are sorted by the `Debug` representation of their `to_default`
+    /// form, in which quantities never appear (`Content::Coin(10)` and `Content::Coin(9)` are both the key
+    /// `Coin(0)`), and then by the smallest coordinate saved for them, by row and then by column
+    pub fn iter_sorted(&self) -> Vec<(&K, &Vec<(ChartedCoordinate, SavedQuantity)>)> {
+        let mut sorted: Vec<&K> = self.map.keys().collect();
+        sorted.sort_by_cached_key(|poi| {
+            let first = self.map[*poi].iter().map(|(c, _)| (c.0, c.1)).min();
+            (format!("{:?}", ChartedMap::key(poi)), first)
+        });
+        sorted.into_iter().map(|poi| (poi, &self.map[poi])).collect()
+    }
+
+    /// returns a copy of the whole map in a reproducible order, meant to be compared in tests: the points of
+    /// interest are ordered as in `iter_sorted` and the entries of each one by row, then by column (entries
+    /// with the same coordinate keep the order they were saved in)
+    pub fn to_sorted_vec(&self) -> Vec<(K, Vec<(ChartedCoordinate, SavedQuantity)>)> {
+        self.iter_sorted()
+            .into_iter()
+            .map(|(poi, entries)| {
+                let mut entries = entries.clone();
+                entries.sort_by_key(|(c, _)| (c.0, c.1));
+                (poi.clone(), entries)
+            })
+            .collect()
+    }
+
+    /// same as `iter_sorted`, but the points of interest are sorted with the provided comparator
+    pub fn iter_sorted_by(
+        &self,
+        compare: impl Fn(&K, &K) -> std::cmp::Ordering,
+    ) -> Vec<(&K, &Vec<(ChartedCoordinate, SavedQuantity)>)> {
+        let mut sorted: Vec<(&K, &Vec<(ChartedCoordinate, SavedQuantity)>)> = self.map.iter().collect();
+        sorted.sort_by(|a, b| compare(a.0, b.0));
+        sorted
+    }
+
+    /// returns an iterator over the distinct points of interest saved so far,
+    /// for example every kind of content found in a `ChartedMap<Content>`
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.map.keys()
+    }
     pub fn save(&mut self, poi: &K, coordinate: &ChartedCoordinate) {
         let num = poi.get_quantity();
-        let poi = poi.to_default();
+        let poi = ChartedMap::key(poi);
         match self.get_mut(&poi) {
             | None => {
                 self.map.insert(poi, vec![(coordinate.clone(), num)]);
@@ -269,59 +367,396 @@ impl<K: MapKey> ChartedMap<K> {
         };
     }
 
+    /// same as `save`, but if the point of interest was already saved at that coordinate its entry is updated
+    /// instead of adding a duplicate, so that observing the same tile tick after tick keeps a single entry.
+    ///
+    /// the saved quantity is overwritten with the new one, not summed: the latest observation is the
+    /// current state of the tile. If several entries share the coordinate, only the first one is updated
+    pub fn save_or_update(&mut self, poi: &K, coordinate: &ChartedCoordinate) {
+        if self.update_quantity(poi, *coordinate, poi.get_quantity()).is_err() {
+            self.save(poi, coordinate);
+        }
+    }
+
+    /// saves all the provided points of interest, like calling `save` on each of them in order,
+    /// but every bucket of the map is looked up only once per distinct point of interest
+    pub fn save_many(&mut self, entries: &[(K, ChartedCoordinate)]) {
+        let mut grouped: HashMap<K, Vec<(ChartedCoordinate, SavedQuantity)>> = HashMap::new();
+        for (poi, coordinate) in entries.iter() {
+            grouped.entry(ChartedMap::key(poi)).or_default().push((*coordinate, poi.get_quantity()));
+        }
+        for (poi, mut found) in grouped.into_iter() {
+            self.map.entry(poi).or_default().append(&mut found);
+        }
+    }
+
+    /// same as `save`, but it also records the tick at which the point of interest was seen,
+    /// so that stale entries can be recognized later on.
+    ///
+    /// saving again the same point of interest at the same coordinate updates its tick
+    pub fn save_at_tick(&mut self, poi: &K, coordinate: &ChartedCoordinate, tick: u64) {
+        self.save(poi, coordinate);
+        self.ticks.insert((ChartedMap::key(poi), *coordinate), tick);
+    }
+
+    /// same as `get`, but every entry also carries the tick at which it was saved,
+    /// `None` if it was saved without one
+    pub fn get_with_ticks(&self, poi: &K) -> Option<Vec<(ChartedCoordinate, SavedQuantity, Option<u64>)>> {
+        let key = ChartedMap::key(poi);
+        self.map.get(&key).map(|entries| {
+            entries
+                .iter()
+                .map(|(c, q)| (*c, q.clone(), self.ticks.get(&(key.clone(), *c)).copied()))
+                .collect()
+        })
+    }
+
+    /// removes every entry that was saved with a tick older than the provided one.
+    /// Entries saved without a tick are kept
+    pub fn remove_older_than(&mut self, tick: u64) {
+        let ticks = &mut self.ticks;
+        for (poi, entries) in self.map.iter_mut() {
+            entries.retain(|(c, _)| match ticks.get(&(poi.clone(), *c)) {
+                | Some(saved) => *saved >= tick,
+                | None => true,
+            });
+        }
+        ticks.retain(|_, saved| *saved >= tick);
+    }
+
     pub fn get(&self, poi: &K) -> Option<&Vec<(ChartedCoordinate, SavedQuantity)>> {
-        self.map.get(&poi.to_default())
+        self.map.get(&ChartedMap::key(poi))
+    }
+
+    /// same as `get`, but only the entries whose quantity is at least `min_quantity` are returned, in saving order.
+    /// Quantities and elevations are compared by value, ranges by their length, while entries
+    /// with `SavedQuantity::None` are always left out
+    pub fn get_above(&self, poi: &K, min_quantity: usize) -> Vec<(ChartedCoordinate, SavedQuantity)> {
+        match self.get(poi) {
+            | None => Vec::new(),
+            | Some(entries) => entries
+                .iter()
+                .filter(|(_, q)| !matches!(q, SavedQuantity::None) && q.value() >= min_quantity)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// returns the coordinates saved for the point of interest, in saving order and without their quantities,
+    /// ready to be given to the ChartedPaths queries. It is empty if the point of interest was never saved
+    pub fn coordinates_of(&self, poi: &K) -> Vec<ChartedCoordinate> {
+        match self.get(poi) {
+            | None => Vec::new(),
+            | Some(entries) => entries.iter().map(|(c, _)| *c).collect(),
+        }
+    }
+
+    /// returns every coordinate saved in the map, whatever its point of interest, sorted by row and then
+    /// by column; a coordinate saved more than once appears only once
+    pub fn all_coordinates(&self) -> Vec<ChartedCoordinate> {
+        let mut coordinates: Vec<ChartedCoordinate> = self.map.values().flatten().map(|(c, _)| *c).collect();
+        coordinates.sort_by_key(|c| (c.0, c.1));
+        coordinates.dedup();
+        coordinates
+    }
+
+    /// returns up to `n` entries saved for the point of interest, sorted by ascending
+    /// manhattan distance from `from` (entries at the same distance keep their saving order).
+    ///
+    /// fewer than `n` entries are returned if not enough were saved, none if the point of interest is unknown
+    pub fn closest_n(&self, poi: &K, from: ChartedCoordinate, n: usize) -> Vec<(ChartedCoordinate, SavedQuantity)> {
+        self.closest_n_by(poi, from, n, DistanceMetric::Manhattan)
+    }
+
+    /// same as `closest_n`, but the distance is measured with the provided metric.
+    ///
+    /// only straight-line distances are considered: walls or water in between are not taken into account
+    pub fn closest_n_by(
+        &self,
+        poi: &K,
+        from: ChartedCoordinate,
+        n: usize,
+        metric: DistanceMetric,
+    ) -> Vec<(ChartedCoordinate, SavedQuantity)> {
+        match self.get(poi) {
+            | None => Vec::new(),
+            | Some(entries) => {
+                let mut sorted = entries.clone();
+                sorted.sort_by(|(a, _), (b, _)| metric.distance(a, &from).total_cmp(&metric.distance(b, &from)));
+                sorted.truncate(n);
+                sorted
+            }
+        }
+    }
+
+    /// returns the top-left and bottom-right corners of the smallest box containing
+    /// every coordinate saved for the point of interest, `None` if there are none
+    pub fn bounding_box(&self, poi: &K) -> Option<(ChartedCoordinate, ChartedCoordinate)> {
+        let entries = self.get(poi)?;
+        let mut iter = entries.iter();
+        let (first, _) = iter.next()?;
+        let (mut top_left, mut bottom_right) = (*first, *first);
+        for (c, _) in iter {
+            top_left = ChartedCoordinate::new(top_left.0.min(c.0), top_left.1.min(c.1));
+            bottom_right = ChartedCoordinate::new(bottom_right.0.max(c.0), bottom_right.1.max(c.1));
+        }
+        Some((top_left, bottom_right))
+    }
+
+    /// groups the coordinates saved for the point of interest into square cells of side `cell_size`,
+    /// the key of each group being `(row / cell_size, col / cell_size)`.
+    ///
+    /// cells on the border of the map may be smaller than the others, and only cells with at least one
+    /// coordinate are present. A `cell_size` of 0 returns an empty map
+    pub fn group_by_region(&self, poi: &K, cell_size: usize) -> HashMap<(usize, usize), Vec<ChartedCoordinate>> {
+        let mut regions: HashMap<(usize, usize), Vec<ChartedCoordinate>> = HashMap::new();
+        if cell_size == 0 {
+            return regions;
+        }
+        if let Some(entries) = self.get(poi) {
+            for (c, _) in entries.iter() {
+                regions.entry((c.0 / cell_size, c.1 / cell_size)).or_default().push(*c);
+            }
+        }
+        regions
+    }
+
+    /// returns the cell of `group_by_region` holding the most coordinates of the point of interest, together
+    /// with how many it holds: the area worth visiting when a resource is scattered around the map.
+    ///
+    /// on equal counts the cell with the smallest key wins. It returns `None` if the point of interest has no
+    /// entries (or if `cell_size` is 0)
+    pub fn densest_region(&self, poi: &K, cell_size: usize) -> Option<((usize, usize), usize)> {
+        self.group_by_region(poi, cell_size)
+            .into_iter()
+            .map(|(cell, coordinates)| (cell, coordinates.len()))
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+    }
+
+    fn key(poi: &K) -> K {
+        // the only form under which points of interest are stored and looked up,
+        // every access to `map` and `ticks` goes through here
+        poi.to_default()
     }
 
     fn get_mut(&mut self, poi: &K) -> Option<&mut Vec<(ChartedCoordinate, SavedQuantity)>> {
-        self.map.get_mut(&poi.to_default())
+        self.map.get_mut(&ChartedMap::key(poi))
     }
 
+    /// returns the coordinate holding the biggest quantity saved for the point of interest, and that quantity.
+    /// Quantities and elevations are compared by value, ranges by their length.
+    ///
+    /// on equal quantities the smallest coordinate (by row, then by column) wins, whatever the saving order.
+    /// If nothing with a quantity was saved the default coordinate is returned with 0
     pub fn get_most(&self, poi: &K) -> Option<(ChartedCoordinate, usize)> {
-        match self.get(poi) {
-            | None => None,
-            | Some(pois) => {
-                let mut coordinate = ChartedCoordinate::default();
-                let mut max = 0usize;
-                for (c, s) in pois.iter() {
-                    match s {
-                        | SavedQuantity::None => {}
-                        | SavedQuantity::ContentQuantity(q) | SavedQuantity::TileElevation(q) => {
-                            if *q > max {
-                                max = *q;
-                                coordinate = ChartedCoordinate::from(c);
-                            }
-                        }
-                        | SavedQuantity::ContentRange(r) => {
-                            if r.len() > max {
-                                max = r.len();
-                                coordinate = ChartedCoordinate::from(c);
-                            }
-                        }
-                    }
+        self.get(poi)?;
+        let most = self.get_most_by(poi, |_, quantity| quantity.value()).filter(|(_, max)| *max > 0);
+        Some(most.unwrap_or((ChartedCoordinate::default(), 0)))
+    }
+
+    /// same as `get_most`, but what "most" means is decided by the provided closure, which scores
+    /// every entry saved for the point of interest: the entry with the highest score is returned
+    /// together with it, ties are broken as in `get_most`.
+    ///
+    /// returns None if the point of interest was never saved
+    ///
+    /// ## Example
+    /// ```
+    /// use charting_tools::ChartingTools;
+    /// use charting_tools::charted_coordinate::ChartedCoordinate;
+    /// use charting_tools::charted_map::{ChartedMap, SavedQuantity};
+    /// use robotics_lib::world::tile::Content;
+    /// use std::cmp::Reverse;
+    ///
+    /// let mut cm = ChartingTools::tool::<ChartedMap<Content>>().unwrap();
+    /// let robot = ChartedCoordinate::new(0, 0);
+    /// // prefer bigger rocks, but closer ones on equal size
+    /// let best = cm.get_most_by(&Content::Rock(0), |c, q| match q {
+    ///     | SavedQuantity::ContentQuantity(n) => (*n, Reverse(ChartedCoordinate::manhattan_distance(&robot, c))),
+    ///     | _ => (0, Reverse(ChartedCoordinate::manhattan_distance(&robot, c))),
+    /// });
+    /// ```
+    pub fn get_most_by<S: Ord>(
+        &self,
+        poi: &K,
+        score: impl Fn(&ChartedCoordinate, &SavedQuantity) -> S,
+    ) -> Option<(ChartedCoordinate, S)> {
+        let mut best: Option<(ChartedCoordinate, S)> = None;
+        for (c, q) in self.get(poi)?.iter() {
+            let current = score(c, q);
+            let better = match &best {
+                | None => true,
+                | Some((best_c, best_score)) => {
+                    current > *best_score || (current == *best_score && (c.0, c.1) < (best_c.0, best_c.1))
                 }
-                Some((coordinate, max))
+            };
+            if better {
+                best = Some((*c, current));
             }
         }
+        best
     }
 
     pub fn remove(&mut self, poi: &K, coordinate: ChartedCoordinate) -> Result<(), u8> {
+        let key = ChartedMap::key(poi);
+        match self.map.get_mut(&key) {
+            | None => Err(1),
+            | Some(found) => match found.iter().position(|(c, _)| *c == coordinate) {
+                | None => Err(2),
+                | Some(i) => {
+                    found.remove(i);
+                    if !found.iter().any(|(c, _)| *c == coordinate) {
+                        self.ticks.remove(&(key, coordinate));
+                    }
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// replaces the quantity saved for the point of interest at the provided coordinate,
+    /// for example after part of a resource has been collected.
+    ///
+    /// like `remove`, it returns `Err(1)` if the point of interest was never saved
+    /// and `Err(2)` if it was never saved at that coordinate. If several entries share the
+    /// coordinate, only the first one is updated
+    pub fn update_quantity(&mut self, poi: &K, coordinate: ChartedCoordinate, new: SavedQuantity) -> Result<(), u8> {
         match self.get_mut(poi) {
             | None => Err(1),
-            | Some(found) => {
-                for (i, (c, _)) in found.iter().enumerate() {
-                    if *c == coordinate {
-                        found.remove(i);
-                        return Ok(());
+            | Some(found) => match found.iter_mut().find(|(c, _)| *c == coordinate) {
+                | None => Err(2),
+                | Some((_, quantity)) => {
+                    *quantity = new;
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// collapses the entries of the point of interest that share the same coordinate into a single one,
+    /// which keeps the position of the first entry and the biggest quantity among the duplicates
+    pub fn dedup(&mut self, poi: &K) {
+        if let Some(entries) = self.get_mut(poi) {
+            ChartedMap::<K>::dedup_entries(entries);
+        }
+    }
+
+    /// same as `dedup`, but for every point of interest saved in the map
+    pub fn dedup_all(&mut self) {
+        for entries in self.map.values_mut() {
+            ChartedMap::<K>::dedup_entries(entries);
+        }
+    }
+
+    fn dedup_entries(entries: &mut Vec<(ChartedCoordinate, SavedQuantity)>) {
+        let mut unique: Vec<(ChartedCoordinate, SavedQuantity)> = Vec::with_capacity(entries.len());
+        for (c, q) in entries.drain(..) {
+            match unique.iter_mut().find(|(u, _)| *u == c) {
+                | None => unique.push((c, q)),
+                | Some((_, kept)) => {
+                    if q.value() > kept.value() {
+                        *kept = q;
+                    }
+                }
+            }
+        }
+        *entries = unique;
+    }
+
+    /// collapses the entries of the point of interest that are close to each other into a single one, for example
+    /// the many coordinates of a single deposit found by a dense scan. Two entries belong to the same cluster if
+    /// their manhattan distance is at most `radius`, or if they are both close to a third one of the cluster.
+    ///
+    /// every cluster is replaced by its member with the biggest quantity (the first saved on equal quantities),
+    /// which gets as quantity the sum of the whole cluster as a `SavedQuantity::ContentQuantity`. Elevations
+    /// are not summed: a `SavedQuantity::TileElevation` representative keeps its own value, and a cluster
+    /// of `SavedQuantity::None` stays `None`. The ticks of the removed entries are dropped
+    pub fn cluster(&mut self, poi: &K, radius: usize) {
+        let key = ChartedMap::key(poi);
+        let entries = match self.map.get_mut(&key) {
+            | None => return,
+            | Some(entries) => entries,
+        };
+        let mut clusters = UnionFind::new(entries.len());
+        for i in 0..entries.len() {
+            for j in i + 1..entries.len() {
+                if ChartedCoordinate::manhattan_distance(&entries[i].0, &entries[j].0) <= radius {
+                    clusters.union(i, j);
+                }
+            }
+        }
+
+        // representative index and summed quantity of every cluster, in order of first appearance
+        let mut merged: Vec<(usize, usize, usize)> = Vec::new();
+        for (i, (_, q)) in entries.iter().enumerate() {
+            let root = clusters.find(i);
+            match merged.iter_mut().find(|(r, _, _)| *r == root) {
+                | None => merged.push((root, i, q.value())),
+                | Some((_, best, sum)) => {
+                    if q.value() > entries[*best].1.value() {
+                        *best = i;
                     }
+                    *sum += q.value();
                 }
-                Err(2)
             }
         }
+        let clustered: Vec<(ChartedCoordinate, SavedQuantity)> = merged
+            .into_iter()
+            .map(|(_, best, sum)| {
+                let (c, q) = &entries[best];
+                let quantity = match q {
+                    | SavedQuantity::None | SavedQuantity::TileElevation(_) => q.clone(),
+                    | _ => SavedQuantity::ContentQuantity(sum),
+                };
+                (*c, quantity)
+            })
+            .collect();
+        *entries = clustered;
+
+        let kept: Vec<ChartedCoordinate> = entries.iter().map(|(c, _)| *c).collect();
+        self.ticks.retain(|(k, c), _| *k != key || kept.contains(c));
+    }
+
+    /// formats only the entries saved for the point of interest, with the same layout used by `Display`,
+    /// so that a single kind of point of interest can be logged without printing the whole map
+    pub fn display_key(&self, poi: &K) -> String {
+        let poi = ChartedMap::key(poi);
+        match self.map.get(&poi) {
+            | None => ChartedMap::<K>::format_entries(&poi, &Vec::new()),
+            | Some(coordinates) => ChartedMap::<K>::format_entries(&poi, coordinates),
+        }
+    }
+
+    fn format_entries(poi: &K, coordinates: &Vec<(ChartedCoordinate, SavedQuantity)>) -> String {
+        let mut s = String::new();
+        s += "Item: ";
+        s += format!("{:?}\n", poi).as_str();
+        for (coordinate, quantity) in coordinates {
+            s += format!("at {} with quantity {}\n", coordinate, quantity).as_str();
+        }
+        s
     }
 
     pub fn clear(&mut self) {
-        self.map.clear()
+        self.map.clear();
+        self.ticks.clear()
+    }
+}
+
+impl ChartedMap<Content> {
+    /// returns, for every kind of content saved, the entry closest to `from` by manhattan distance,
+    /// giving a complete picture of what is nearby in a single call.
+    ///
+    /// on equal distance the entry saved first wins, contents without any entry left are not present
+    pub fn nearest_of_each(&self, from: ChartedCoordinate) -> HashMap<Content, (ChartedCoordinate, SavedQuantity)> {
+        let mut nearest = HashMap::new();
+        for (content, entries) in self.map.iter() {
+            let closest = entries.iter().min_by_key(|(c, _)| ChartedCoordinate::manhattan_distance(&from, c));
+            if let Some((c, q)) = closest {
+                nearest.insert(content.clone(), (*c, q.clone()));
+            }
+        }
+        nearest
     }
 }
 
@@ -330,12 +765,156 @@ impl<K: MapKey> Display for ChartedMap<K> {
         let mut s = String::new();
         s += "The Charted map contains:\n";
         for (poi, coordinates) in self.map.iter() {
-            s += "Item: ";
-            s += format!("{:?}\n", poi).as_str();
-            for (coordinate, quantity) in coordinates {
-                s += format!("at {} with quantity {}\n", coordinate, quantity).as_str();
-            }
+            s += ChartedMap::<K>::format_entries(poi, coordinates).as_str();
         }
         write!(f, "{}", s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_many_matches_save() {
+        // every kind of quantity, with repeated keys and coordinates spread across the buckets
+        let entries: Vec<(Content, ChartedCoordinate)> = (0..1000)
+            .map(|i| {
+                let content = match i % 5 {
+                    | 0 => Content::Rock(i),
+                    | 1 => Content::Tree(i % 7),
+                    | 2 => Content::Coin(i),
+                    | 3 => Content::Bin(i..i + 3),
+                    | _ => Content::Fire,
+                };
+                (content, ChartedCoordinate(i % 37, i / 37))
+            })
+            .collect();
+
+        let mut saved = ChartedMap::from_entries(Vec::new());
+        for (content, coordinate) in entries.iter() {
+            saved.save(content, coordinate);
+        }
+        let mut saved_many = ChartedMap::from_entries(Vec::new());
+        saved_many.save_many(&entries);
+
+        // SavedQuantity::None never equals itself, so the entries are compared by what they hold
+        let flat = |map: &ChartedMap<Content>, key: &Content| -> Vec<(ChartedCoordinate, bool, Option<usize>)> {
+            map.get(key).unwrap().iter().map(|(c, q)| (*c, q.is_nome(), q.as_usize())).collect()
+        };
+        assert_eq!(saved_many.keys().count(), saved.keys().count());
+        for key in saved.keys() {
+            assert_eq!(flat(&saved_many, key), flat(&saved, key));
+        }
+        assert_eq!(saved_many.keys().count(), 5);
+        assert_eq!(saved_many.get(&Content::Rock(0)).unwrap().len(), 200);
+    }
+
+    #[test]
+    fn iter_sorted_ignores_quantities_and_saving_order() {
+        let entries = vec![
+            (Content::Rock(2), ChartedCoordinate(2, 0)),
+            (Content::Coin(10), ChartedCoordinate(1, 1)),
+            (Content::Coin(9), ChartedCoordinate(0, 3)),
+            (Content::Bin(0..4), ChartedCoordinate(3, 3)),
+        ];
+        let forward = ChartedMap::from_entries(entries.clone());
+        let backward = ChartedMap::from_entries(entries.into_iter().rev().collect());
+
+        let keys = |map: &ChartedMap<Content>| -> Vec<Content> {
+            map.iter_sorted().into_iter().map(|(k, _)| k.clone()).collect()
+        };
+        let expected = [Content::Bin(0..4), Content::Coin(1), Content::Rock(1)].map(|c| c.to_default()).to_vec();
+        assert_eq!(keys(&forward), expected);
+        assert_eq!(keys(&backward), keys(&forward));
+        assert_eq!(forward.iter_sorted()[1].1.len(), 2);
+    }
+
+    #[test]
+    fn save_and_get_agree_on_the_key() {
+        let tile = |content: Content, elevation: usize| Tile { tile_type: TileType::Grass, content, elevation };
+        let mut tiles = ChartedMap::from_entries(vec![
+            (tile(Content::Rock(3), 2), ChartedCoordinate(0, 0)),
+            (tile(Content::Rock(5), 4), ChartedCoordinate(1, 1)),
+            (tile(Content::Tree(1), 2), ChartedCoordinate(2, 2)),
+        ]);
+        // elevation and content quantity are not part of the key
+        let rocks = tiles.get(&tile(Content::Rock(7), 9)).unwrap();
+        assert_eq!(rocks.len(), 2);
+        assert_eq!(rocks[0], (ChartedCoordinate(0, 0), SavedQuantity::TileElevation(2)));
+        assert_eq!(rocks[1], (ChartedCoordinate(1, 1), SavedQuantity::TileElevation(4)));
+        assert_eq!(tiles.get(&tile(Content::Tree(0), 0)).unwrap().len(), 1);
+        assert_eq!(tiles.keys().count(), 2);
+        assert_eq!(tiles.remove(&tile(Content::Rock(1), 0), ChartedCoordinate(1, 1)), Ok(()));
+        assert_eq!(tiles.get(&tile(Content::Rock(3), 2)).unwrap().len(), 1);
+
+        let contents = ChartedMap::from_entries(vec![
+            (Content::Rock(3), ChartedCoordinate(0, 0)),
+            (Content::Bin(0..3), ChartedCoordinate(1, 1)),
+        ]);
+        assert_eq!(contents.get(&Content::Rock(0)).unwrap()[0].1, SavedQuantity::ContentQuantity(3));
+        assert_eq!(contents.get(&Content::Bin(5..9)).unwrap()[0].1, SavedQuantity::ContentRange(0..3));
+        assert!(contents.get(&Content::Tree(3)).is_none());
+
+        // tile types are keys as they are, teleports are told apart by their activation
+        let types = ChartedMap::from_entries(vec![
+            (TileType::Teleport(true), ChartedCoordinate(0, 0)),
+            (TileType::Sand, ChartedCoordinate(1, 1)),
+        ]);
+        assert_eq!(types.coordinates_of(&TileType::Teleport(true)), vec![ChartedCoordinate(0, 0)]);
+        assert!(types.get(&TileType::Teleport(false)).is_none());
+        assert!(types.get(&TileType::Sand).unwrap()[0].1.is_nome());
+    }
+
+    #[test]
+    fn saved_quantity_as_usize() {
+        assert_eq!(SavedQuantity::None.as_usize(), None);
+        assert_eq!(SavedQuantity::ContentQuantity(4).as_usize(), Some(4));
+        assert_eq!(SavedQuantity::TileElevation(0).as_usize(), Some(0));
+        assert_eq!(SavedQuantity::TileElevation(12).as_usize(), Some(12));
+        // ranges count as their length
+        assert_eq!(SavedQuantity::ContentRange(2..7).as_usize(), Some(5));
+        assert_eq!(SavedQuantity::ContentRange(3..3).as_usize(), Some(0));
+    }
+
+    #[test]
+    fn cluster_chains_entries_and_drops_their_ticks() {
+        // (0, 0) and (0, 4) are too far apart, but both are close to (0, 2)
+        let mut rocks = ChartedMap::from_entries(Vec::new());
+        rocks.save_at_tick(&Content::Rock(1), &ChartedCoordinate(0, 0), 1);
+        rocks.save_at_tick(&Content::Rock(3), &ChartedCoordinate(0, 2), 2);
+        rocks.save_at_tick(&Content::Rock(2), &ChartedCoordinate(0, 4), 3);
+        rocks.save_at_tick(&Content::Rock(7), &ChartedCoordinate(5, 5), 4);
+        rocks.save(&Content::Tree(1), &ChartedCoordinate(0, 1));
+        rocks.cluster(&Content::Rock(0), 2);
+
+        let clustered = rocks.get_with_ticks(&Content::Rock(0)).unwrap();
+        assert_eq!(clustered.len(), 2);
+        assert_eq!(clustered[0], (ChartedCoordinate(0, 2), SavedQuantity::ContentQuantity(6), Some(2)));
+        assert_eq!(clustered[1], (ChartedCoordinate(5, 5), SavedQuantity::ContentQuantity(7), Some(4)));
+        assert_eq!(rocks.ticks.len(), 2);
+        // the other points of interest are left alone
+        assert_eq!(rocks.get(&Content::Tree(0)).unwrap().len(), 1);
+
+        // elevations are not summed
+        let tile = |elevation: usize| Tile { tile_type: TileType::Hill, content: Content::None, elevation };
+        let mut hills = ChartedMap::from_entries(vec![
+            (tile(1), ChartedCoordinate(0, 0)),
+            (tile(4), ChartedCoordinate(0, 1)),
+            (tile(2), ChartedCoordinate(1, 1)),
+        ]);
+        hills.cluster(&tile(0), 1);
+        assert_eq!(hills.get(&tile(0)).unwrap(), &vec![(ChartedCoordinate(0, 1), SavedQuantity::TileElevation(4))]);
+
+        // a cluster without quantities keeps none
+        let mut fires = ChartedMap::from_entries(vec![
+            (Content::Fire, ChartedCoordinate(3, 3)),
+            (Content::Fire, ChartedCoordinate(3, 4)),
+        ]);
+        fires.cluster(&Content::Fire, 1);
+        let fire = fires.get(&Content::Fire).unwrap();
+        assert_eq!(fire.len(), 1);
+        assert_eq!(fire[0].0, ChartedCoordinate(3, 3));
+        assert!(fire[0].1.is_nome());
+    }
+}
@@ -1,16 +1,26 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 
 use petgraph::{Graph, Undirected};
 use petgraph::algo::{astar, dijkstra};
 use petgraph::graph::{EdgeIndex, NodeIndex, UnGraph};
+use petgraph::unionfind::UnionFind;
+use petgraph::visit::{Bfs, EdgeFiltered, EdgeRef};
 use robotics_lib::interface::Direction;
-use robotics_lib::interface::look_at_sky;
+use robotics_lib::interface::{look_at_sky, robot_map};
 use robotics_lib::utils::calculate_cost_go_with_environment;
-use robotics_lib::world::tile::{Tile, TileType};
+use robotics_lib::world::tile::{Content, Tile, TileType};
 use robotics_lib::world::World;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::{ChartingTool, NUMBER, reserved::New};
+use crate::{ChartingTool, Slot, reserved::New};
+#[cfg(feature = "serde")]
+use crate::ChartingTools;
 use crate::charted_coordinate::ChartedCoordinate;
+use crate::charted_map::ChartedMap;
 
 /// -----Welcome to the ChartedPaths!-----
 ///
@@ -36,13 +46,47 @@ use crate::charted_coordinate::ChartedCoordinate;
 ///          represents the undirected graph
 ///          the node contains a ChartedCoordinate (usize,usize) of the discovered tile
 ///          the edge between two nodes is the cost (u32) that the robot should spend by going
-///          from the coordinate of node_1 to the coordinate of node_2
+///          from the coordinate of node_1 to the coordinate of node_2.
+///          Moving is not symmetric: going uphill costs the base cost plus the square of the
+///          elevation difference, while going downhill (or on flat ground) costs only the base cost,
+///          robotics_lib doesn't refund any energy on descents. Since a single edge has to represent
+///          both directions, its weight is the most expensive of the two readings, so the
+///          costs returned by the tool never underestimate the energy actually needed.
 ///- `indexes: Vec<Vec<Option<NodeIndex>>>`.
 ///          it's a map nxn that contains the "conversion" of the map's coordinates to their
 ///          references in the graph.
 ///- `teleports_edges: HashMap<EdgeIndex, bool>`.
 ///          contains the references to the edges in which one of the node is a teleport. Can be
 ///          used for further development or to easily changing the cost of teleport operations.
+///- `content_cost: bool`.
+///          private flag, set with `set_content_cost`, that tells `init` to add the energy needed
+///          to clear obstacle contents to the edge weights.
+///- `base_costs: HashMap<TileType, u32>`.
+///          private, set with `set_base_costs`, the base traversal costs that `init` uses instead of
+///          robotics_lib's ones for the given tile types.
+///- `walkable: Option<Arc<dyn Fn(&Tile) -> bool + Send + Sync>>`.
+///          private, the predicate given to `init_with_walkability`, None when the graph was built
+///          with robotics_lib's `walk()` property. `refresh` uses it to decide the walkability of the
+///          changed tiles.
+///- `content_extras: HashMap<ChartedCoordinate, u32>`.
+///          private, the clearing cost of the contents saved in the ChartedMap given to
+///          `init_with_content_map`, added to every edge touching their tile, also by `refresh`.
+///- `teleports: Vec<ChartedCoordinate>`.
+///          private, the coordinates of the working teleports found during `init`.
+///- `use_heuristic: bool`, `min_cost: u32` and `min_teleport_cost: u32`.
+///          private, the A* queries use the manhattan distance times the cheapest edge weight
+///          (computed during `init`) as heuristic, unless disabled via `set_heuristic`. When there are
+///          teleports the estimate is capped at the cheapest teleport edge, which may be a shortcut.
+///- `elevations: Vec<Vec<Option<usize>>>`.
+///          private, the elevation of every discovered tile, used to limit the climbing of a path.
+///- `home: Option<(ChartedCoordinate, HashMap<ChartedCoordinate, u32>)>`.
+///          private, the coordinate set with `set_home` and the cost to reach it from every node,
+///          cleared by `init`.
+///- `next_cell: Option<usize>`.
+///          private, the row-major index of the next cell of the robot_map to be processed by
+///          `init_incremental`, None when no incremental construction is in progress.
+///- `slot: Slot`.
+///          private, the slot taken among the active tools: clones and forks do not hold one.
 ///
 ///  ##     Example:
 /// ```
@@ -52,37 +96,194 @@ use crate::charted_coordinate::ChartedCoordinate;
 ///          let mut charted_path = ChartingTools::tool::<ChartedPaths>().unwrap();
 ///
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ChartedPaths {
     pub graph: Graph<ChartedCoordinate, u32, Undirected>,
     pub indexes: Vec<Vec<Option<NodeIndex>>>,
     pub teleports_edges: HashMap<EdgeIndex, bool>,
+    content_cost: bool,
+    base_costs: HashMap<TileType, u32>,
+    walkable: Option<Arc<dyn Fn(&Tile) -> bool + Send + Sync>>,
+    content_extras: HashMap<ChartedCoordinate, u32>,
+    teleports: Vec<ChartedCoordinate>,
+    use_heuristic: bool,
+    min_cost: u32,
+    min_teleport_cost: u32,
+    elevations: Vec<Vec<Option<usize>>>,
+    home: Option<(ChartedCoordinate, HashMap<ChartedCoordinate, u32>)>,
+    next_cell: Option<usize>,
+    slot: Slot,
+}
+
+/// # Enum: PathAlgorithm
+/// the algorithm used by `ChartedPaths::cost` to search the graph
+/// - Dijkstra: explores the tiles in order of cost from the start
+/// - AStar: same, but guided toward the destination by the heuristic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathAlgorithm {
+    Dijkstra,
+    AStar,
+}
+
+/// # Enum: PathInstruction
+/// a single move of the plan returned by `ChartedPaths::shortest_path_instructions`
+/// - Step(direction): walk to the adjacent tile in that direction
+/// - Teleport { from, to }: use the teleport at `from` to reach the one at `to`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathInstruction {
+    Step(Direction),
+    Teleport { from: ChartedCoordinate, to: ChartedCoordinate },
+}
+
+/// # Enum: TieBreak
+/// how `ChartedPaths::shortest_path_tie_break` chooses among paths with the same minimum cost
+/// - FewestHops: the path with the fewest steps, then the one with the smallest coordinates
+/// - SmallestCoordinates: the path whose sequence of coordinates is the smallest, comparing them by row
+///   and then by column: at every step the smallest next coordinate is taken, going back to the previous
+///   choice when it leads nowhere (which can only happen along edges costing 0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    FewestHops,
+    SmallestCoordinates,
+}
+
+/// plain representation of a ChartedPaths used for the JSON export: nodes and edges are listed
+/// in graph index order, so that rebuilding them gives back the same indexes
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SerializedPaths {
+    nodes: Vec<(usize, usize)>,
+    edges: Vec<(usize, usize, u32)>,
+    teleports_edges: Vec<usize>,
+    indexes: Vec<Vec<Option<usize>>>,
+    teleports: Vec<(usize, usize)>,
+    content_cost: bool,
+    use_heuristic: bool,
+    min_cost: u32,
+    #[serde(default)]
+    elevations: Vec<Vec<Option<usize>>>,
+    #[serde(default)]
+    base_costs: Vec<(TileType, u32)>,
+    #[serde(default)]
+    content_extras: Vec<((usize, usize), u32)>,
+}
+
+/// a single step of a path in the JSON export
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SerializedStep {
+    row: usize,
+    col: usize,
 }
 
 impl Drop for ChartedPaths {
     fn drop(&mut self) {
-        if let Ok(mut n) = NUMBER.lock() {
-            if *n > 0 {
-                *n = *n - 1;
-            }
-        }
+        self.slot.release();
     }
 }
 
 impl ChartingTool for ChartedPaths {}
 
+/// the graph and the indexes of a real map are far too big to be printed,
+/// only their size is shown: the raw fields are still public for whoever needs them
+impl Debug for ChartedPaths {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChartedPaths")
+            .field("dimension", &self.indexes.len())
+            .field("nodes", &self.graph.node_count())
+            .field("edges", &self.graph.edge_count())
+            .field("teleports", &self.teleports.len())
+            .field("teleports_edges", &self.teleports_edges.len())
+            .field("content_cost", &self.content_cost)
+            .field("base_costs", &self.base_costs)
+            .field("use_heuristic", &self.use_heuristic)
+            .field("home", &self.home.as_ref().map(|(home, _)| *home))
+            .finish()
+    }
+}
+
 impl New for ChartedPaths {
     fn new() -> Self {
         ChartedPaths {
             graph: Default::default(),
             indexes: Vec::new(),
             teleports_edges: HashMap::new(),
+            content_cost: false,
+            base_costs: HashMap::new(),
+            walkable: None,
+            content_extras: HashMap::new(),
+            teleports: Vec::new(),
+            use_heuristic: true,
+            min_cost: 0,
+            min_teleport_cost: 0,
+            elevations: Vec::new(),
+            home: None,
+            next_cell: None,
+            slot: Slot::acquired(),
         }
     }
 }
 
 #[allow(unused)]
 impl ChartedPaths {
+    ///     Enables or disables the content-clearing cost in the edge weights (disabled by default).
+    ///     It has to be called before `init`, since the weights are evaluated while building the graph.
+    ///
+    ///     When enabled, stepping onto a tile whose content is an obstacle adds the energy needed to
+    ///     destroy that content, as given by robotics_lib's content properties. The contents
+    ///     treated as obstacles are:
+    ///     - `Content::Rock`
+    ///     - `Content::Tree`
+    ///     - `Content::Bush`
+    ///     - `Content::Fire`
+    ///
+    ///     every other content is considered something the robot can simply walk over.
+    ///     ### Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let mut charted_path = ChartingTools::tool::<ChartedPaths>().unwrap();
+    ///             charted_path.set_content_cost(true);
+    ///             charted_path.init(&robot_map(world).unwrap(), world);
+    ///         }
+    pub fn set_content_cost(&mut self, enabled: bool) {
+        self.content_cost = enabled;
+    }
+
+    ///     Replaces the base traversal cost of the given tile types, so that alternative energy models can
+    ///     be tried without touching robotics_lib. Like `set_content_cost`, it has to be called before `init`.
+    ///
+    ///     The cost in the table takes the place of `tile_type.properties().cost()`, the weather and the
+    ///     elevation are then applied to it as usual; tile types missing from the table keep robotics_lib's
+    ///     cost. With None the overrides are removed.
+    ///     The table is kept by the tool: every following `init`, `init_with_*`, `init_incremental` and `refresh`
+    ///     uses it until it is replaced or removed, and `to_json`/`from_json` carry it along.
+    ///     ### Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let mut charted_path = ChartingTools::tool::<ChartedPaths>().unwrap();
+    ///             // what if sand was as cheap as grass?
+    ///             charted_path.set_base_costs(Some(HashMap::from([(TileType::Sand, 1)])));
+    ///             charted_path.init(&robot_map(world).unwrap(), world);
+    ///         }
+    pub fn set_base_costs(&mut self, costs: Option<HashMap<TileType, u32>>) {
+        self.base_costs = costs.unwrap_or_default();
+    }
+
+    ///     Returns a copy of the graph that can be modified freely, for example to try removing some nodes or
+    ///     changing some weights and see how the paths change, and then be thrown away.
+    ///     A fork is not a new tool: it does not count toward the limit of active tools and dropping it does
+    ///     not free the slot of the original. The same holds for `clone`, which it is equivalent to.
+    ///     ### Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let mut attempt = charted_path.fork();
+    ///             attempt.remove_node(ChartedCoordinate(3, 3)).unwrap();
+    ///             let detour = attempt.shortest_path_cost(my_coordinate, goal);
+    ///         }
+    pub fn fork(&self) -> ChartedPaths {
+        self.clone()
+    }
+
     ///     Robotic_lib provides a function called robot_map(..) that returns a matrix nxn in which
     ///     are "stored" the discovered tiles (seen or walked over) of the robot while
     ///     the other ones are set to None.
@@ -103,24 +304,183 @@ impl ChartedPaths {
     ///              let mut charted_path = ChartingTools::tool::<ChartedPaths>().unwrap();
     ///             charted_path.init(&robot_map(world).unwrap(), world);
     ///         }
+    ///
+    ///     An empty map, or one without any walkable tile, produces an empty graph: every query
+    ///     on it will simply return None.
     pub fn init(&mut self, robot_map: &Vec<Vec<Option<Tile>>>, world: &World) {
-        self.graph = UnGraph::<ChartedCoordinate, u32>::new_undirected();
+        self.walkable = None;
+        self.content_extras.clear();
+        self.build(robot_map, world);
+    }
+
+    ///     Same as init, but the contents saved in the ChartedMap are taken into account as well: every
+    ///     edge touching a tile where an obstacle content has been saved (`Content::Rock`, `Content::Tree`,
+    ///     `Content::Bush` or `Content::Fire`, as in `set_content_cost`) gets the energy needed to destroy it
+    ///     added to its weight, so routes prefer tiles that are already cleared or have no content at all.
+    ///     The extra cost is the one given by robotics_lib's content properties and does not depend on the
+    ///     quantity saved: destroying the content costs the same whatever its size.
+    ///     Since edges are undirected, the cost is paid both entering and leaving the tile.
+    ///     With None it is exactly init.
+    ///     ***NOTE***: it is meant as an alternative to `set_content_cost`, enabling both makes the robot pay
+    ///     twice for the contents that are present in both the robot_map and the ChartedMap.
+    ///     ### Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let mut charted_path = ChartingTools::tool::<ChartedPaths>().unwrap();
+    ///             charted_path.init_with_content_map(&robot_map(world).unwrap(), world, Some(&self.obstacles));
+    ///         }
+    pub fn init_with_content_map(
+        &mut self,
+        robot_map: &Vec<Vec<Option<Tile>>>,
+        world: &World,
+        content_map: Option<&ChartedMap<Content>>,
+    ) {
+        self.walkable = None;
+        self.content_extras.clear();
+        for (content, entries) in content_map.iter().flat_map(|content_map| content_map.iter()) {
+            let cost = ChartedPaths::clearing_cost(content);
+            if cost == 0 {
+                continue;
+            }
+            for (coordinate, _) in entries.iter() {
+                // a tile holds a single content, so it is charged once even if saved many times
+                let saved = self.content_extras.entry(*coordinate).or_insert(0);
+                *saved = (*saved).max(cost);
+            }
+        }
+        self.build(robot_map, world);
+    }
+
+    ///     Same as init, but only the tiles inside the square of side `2 * radius + 1` centered on `center`
+    ///     become nodes of the graph, the rest of the robot_map is ignored as if it was undiscovered.
+    ///     On large worlds this produces a much smaller graph, which is useful to re-plan often in the area
+    ///     around the robot. Queries involving coordinates outside the region return None.
+    ///     ***NOTE***: paths can only go through the region, so a route between two tiles of the region
+    ///     is the same of the full graph only if the best one never leaves it.
+    ///     ### Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let mut charted_path = ChartingTools::tool::<ChartedPaths>().unwrap();
+    ///             charted_path.init_region(&robot_map(world).unwrap(), world, my_coordinate, 10);
+    ///         }
+    pub fn init_region(
+        &mut self,
+        robot_map: &Vec<Vec<Option<Tile>>>,
+        world: &World,
+        center: ChartedCoordinate,
+        radius: usize,
+    ) {
+        let region: Vec<Vec<Option<Tile>>> = robot_map
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(j, tile)| {
+                        if i.abs_diff(center.0) <= radius && j.abs_diff(center.1) <= radius {
+                            tile.clone()
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        self.init(&region, world);
+    }
+
+    ///     Same as init, but only the coordinates in `confirmed` can become nodes of the graph, every other
+    ///     tile of the robot_map is ignored as if it was undiscovered. The robot_map does not tell apart the
+    ///     tiles the robot walked on from the ones it only saw or discovered, whose content may be outdated:
+    ///     a cautious robot can keep track of the tiles it trusts and route only across them.
+    ///     ### Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             self.walked.insert(ChartedCoordinate::from(self.get_coordinate()));
+    ///             let mut charted_path = ChartingTools::tool::<ChartedPaths>().unwrap();
+    ///             charted_path.init_confirmed(&robot_map(world).unwrap(), world, &self.walked);
+    ///         }
+    pub fn init_confirmed(
+        &mut self,
+        robot_map: &Vec<Vec<Option<Tile>>>,
+        world: &World,
+        confirmed: &HashSet<ChartedCoordinate>,
+    ) {
+        let trusted: Vec<Vec<Option<Tile>>> = robot_map
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(j, tile)| {
+                        if confirmed.contains(&ChartedCoordinate(i, j)) {
+                            tile.clone()
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        self.init(&trusted, world);
+    }
+
+    ///     Same as init, but which tiles become nodes of the graph is decided by the provided predicate
+    ///     instead of robotics_lib's `walk()` property: tiles for which it returns true are considered
+    ///     walkable. It can be used to cross tiles the library considers not walkable (for example if
+    ///     the robot has some special equipment) or to avoid walkable but dangerous ones.
+    ///     The predicate is kept by the tool, so that `refresh` can apply it to the tiles that change.
+    ///     ### Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let mut charted_path = ChartingTools::tool::<ChartedPaths>().unwrap();
+    ///             // this robot can swim
+    ///             charted_path.init_with_walkability(&robot_map(world).unwrap(), world, |tile| {
+    ///                 tile.tile_type.properties().walk() || tile.tile_type == TileType::DeepWater
+    ///             });
+    ///         }
+    pub fn init_with_walkability(
+        &mut self,
+        robot_map: &Vec<Vec<Option<Tile>>>,
+        world: &World,
+        walkable: impl Fn(&Tile) -> bool + Send + Sync + 'static,
+    ) {
+        self.walkable = Some(Arc::new(walkable));
+        self.content_extras.clear();
+        self.build(robot_map, world);
+    }
 
-        let mut teleports = Vec::new();
+    fn build(&mut self, robot_map: &Vec<Vec<Option<Tile>>>, world: &World) {
+        // the graph of init, init_with_content_map and init_with_walkability: which tiles are walkable and
+        // the extra costs of the contents have already been stored
+        self.graph = UnGraph::<ChartedCoordinate, u32>::new_undirected();
+        self.indexes.clear();
+        self.teleports_edges.clear();
 
-        let dimension = robot_map.len(); //the world is a square
+        self.teleports.clear();
+        self.home = None;
+        self.next_cell = None;
+        self.elevations =
+            robot_map.iter().map(|row| row.iter().map(|tile| tile.as_ref().map(|t| t.elevation)).collect()).collect();
 
+        let walkable = self.walkable.clone();
+        let walk = |tile: &Tile| tile.tile_type.properties().walk();
+        let walkable: &dyn Fn(&Tile) -> bool = match walkable.as_deref() {
+            | Some(walkable) => walkable,
+            | None => &walk,
+        };
         ChartedPaths::adds_nodes(
             &robot_map,
-            dimension,
             &mut self.indexes,
             &mut self.graph,
-            &mut teleports,
+            &mut self.teleports,
+            walkable,
         );
 
-        // Add vertices
-        for i in 0..dimension {
-            for j in 0..dimension {
+        // Add vertices, the rows of the map are not required to be as many as the columns
+        for i in 0..self.indexes.len() {
+            for j in 0..self.indexes[i].len() {
                 // check if the robot discovered that Tile
                 match self.indexes[i][j].as_ref() {
                     | None => {}
@@ -130,46 +490,40 @@ impl ChartedPaths {
                         // nodes have not been added
 
                         // CHECK RIGHT NODE
-                        if j != dimension - 1 {
+                        if j + 1 < self.indexes[i].len() {
                             // border check
                             match self.indexes[i][j + 1].as_ref() {
                                 | None => {}
                                 | Some(next_tile) => {
                                     // this checks if the robot walked over the tile or if he has
                                     // seen it.
-                                    self.graph.add_edge(
-                                        *present_tile,
-                                        *next_tile,
-                                        ChartedPaths::eval_weight(
-                                            &ChartedCoordinate(i, j),
-                                            &ChartedCoordinate(i, j + 1),
-                                            &robot_map,
-                                            &world,
-                                        ),
+                                    let weight = self.terrain_weight(
+                                        &ChartedCoordinate(i, j),
+                                        &ChartedCoordinate(i, j + 1),
+                                        &robot_map,
+                                        &world,
                                     );
+                                    self.graph.add_edge(*present_tile, *next_tile, weight);
                                 }
                             }
                         }
                         // CHECK NODE BELOW
-                        if i != dimension - 1 {
-                            // border check
-                            match self.indexes[i + 1][j].as_ref() {
+                        if i + 1 < self.indexes.len() {
+                            // border check, the row below may also be shorter
+                            match self.indexes[i + 1].get(j).and_then(|node| node.as_ref()) {
                                 | None => {}
                                 | Some(next_tile) => {
                                     // this checks if the robot walked over the tile or if he has
                                     // seen it. but it also checks the walk-ability, since, not walkable
                                     // nodes have not been added
 
-                                    self.graph.add_edge(
-                                        *present_tile,
-                                        *next_tile,
-                                        ChartedPaths::eval_weight(
-                                            &ChartedCoordinate(i, j),
-                                            &ChartedCoordinate(i + 1, j),
-                                            &robot_map,
-                                            &world,
-                                        ),
+                                    let weight = self.terrain_weight(
+                                        &ChartedCoordinate(i, j),
+                                        &ChartedCoordinate(i + 1, j),
+                                        &robot_map,
+                                        &world,
                                     );
+                                    self.graph.add_edge(*present_tile, *next_tile, weight);
                                 }
                             }
                         }
@@ -178,17 +532,242 @@ impl ChartedPaths {
             }
         }
 
+        self.connect_teleports();
+    }
+
+    ///     Same as init, but the graph is built a piece at a time: every call adds at most `max_nodes` nodes,
+    ///     together with their edges, and returns true once the whole robot_map has been processed. On very
+    ///     large maps this spreads the construction over several ticks instead of spending a single tick on it.
+    ///     The first call starts a new construction, which the following ones continue from where the previous
+    ///     one stopped; after it returns true, the next call starts over. Until then the graph is incomplete,
+    ///     so queries may miss paths that go through the part of the map not processed yet.
+    ///     ***NOTE***: the same robot_map has to be passed to every call of a construction, calling `init`
+    ///     in the meantime discards the progress. At least one node is added per call.
+    ///     The walkability predicate and the content costs of the last `init_with_walkability` or
+    ///     `init_with_content_map` are kept, so the finished graph is the one that call would have built;
+    ///     call `init` first to go back to the tiles the library considers walkable.
+    ///     ### Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             if !self.graph_ready {
+    ///                 self.graph_ready = self.charted_path.init_incremental(&self.map_snapshot, world, 500);
+    ///             }
+    ///         }
+    pub fn init_incremental(&mut self, robot_map: &Vec<Vec<Option<Tile>>>, world: &World, max_nodes: usize) -> bool {
+        // the cells are counted row after row, each row as long as it is in the map
+        let cells: usize = robot_map.iter().map(|row| row.len()).sum();
+        let start = match self.next_cell {
+            | Some(cell) => cell,
+            | None => {
+                self.graph = UnGraph::<ChartedCoordinate, u32>::new_undirected();
+                self.teleports_edges.clear();
+                self.teleports.clear();
+                self.home = None;
+                self.indexes = robot_map.iter().map(|row| vec![None; row.len()]).collect();
+                self.elevations = robot_map
+                    .iter()
+                    .map(|row| row.iter().map(|tile| tile.as_ref().map(|t| t.elevation)).collect())
+                    .collect();
+                0
+            }
+        };
+
+        // row and column of the first cell to process
+        let (mut i, mut j) = (0, start);
+        while i < robot_map.len() && j >= robot_map[i].len() {
+            j -= robot_map[i].len();
+            i += 1;
+        }
+
+        let mut added = 0;
+        let mut cell = start;
+        while cell < cells && added < max_nodes.max(1) {
+            if j >= robot_map[i].len() {
+                (i, j) = (i + 1, 0);
+                continue;
+            }
+            let (row, col) = (i, j);
+            cell += 1;
+            j += 1;
+            let tile = match robot_map[row][col].as_ref() {
+                | Some(tile) if self.is_walkable(tile) => tile,
+                | _ => continue,
+            };
+            let node = self.graph.add_node(ChartedCoordinate(row, col));
+            self.indexes[row][col] = Some(node);
+            if tile.tile_type == TileType::Teleport(true) {
+                self.teleports.push(ChartedCoordinate(row, col));
+            }
+            added += 1;
+            // the cells above and on the left have already been processed, link the new node to them.
+            // The row above may be shorter than this one
+            let previous = [(row.checked_sub(1), Some(col)), (Some(row), col.checked_sub(1))];
+            for (other_row, other_col) in previous {
+                if let (Some(other_row), Some(other_col)) = (other_row, other_col) {
+                    if let Some(Some(other)) = self.indexes[other_row].get(other_col).copied() {
+                        let weight = self.terrain_weight(
+                            &ChartedCoordinate(other_row, other_col),
+                            &ChartedCoordinate(row, col),
+                            robot_map,
+                            world,
+                        );
+                        self.graph.add_edge(other, node, weight);
+                    }
+                }
+            }
+        }
+
+        if cell < cells {
+            self.next_cell = Some(cell);
+            return false;
+        }
+        self.next_cell = None;
+        self.connect_teleports();
+        true
+    }
+
+    fn connect_teleports(&mut self) {
+        // exactly one edge for every unordered pair of teleports: each one is connected to those after it
+        // in the list, so with fewer than two teleports there is nothing to do
+        let teleports = &self.teleports;
         for (index, current_teleport) in teleports.iter().enumerate() {
-            for i in index..teleports.len() - 1 {
-                let next_teleport = teleports[i + 1];
+            for next_teleport in teleports[index + 1..].iter() {
+                // teleport always consumes 30 energy, the contents to clear on the two ends come on top
+                let weight = 30u32
+                    .saturating_add(self.content_extra(current_teleport))
+                    .saturating_add(self.content_extra(next_teleport));
                 let teleports_edge = self.graph.add_edge(
                     self.indexes[current_teleport.0][current_teleport.1].unwrap(),
                     self.indexes[next_teleport.0][next_teleport.1].unwrap(),
-                    30,
-                ); // teleport always consumes 30 energy
+                    weight,
+                );
                 self.teleports_edges.insert(teleports_edge, true);
             }
         }
+        self.update_min_costs();
+    }
+
+    fn update_min_costs(&mut self) {
+        // cheapest step on the terrain and cheapest teleport, used by the A* heuristic
+        let (mut terrain, mut teleport): (Option<u32>, Option<u32>) = (None, None);
+        for edge in self.graph.edge_references() {
+            let min = if self.teleports_edges.contains_key(&edge.id()) { &mut teleport } else { &mut terrain };
+            *min = Some(min.map_or(*edge.weight(), |current| current.min(*edge.weight())));
+        }
+        self.min_cost = terrain.unwrap_or(0);
+        self.min_teleport_cost = teleport.unwrap_or(0);
+    }
+
+    ///     Updates a graph built by `init` on `old_map` so that it matches `new_map`, touching only the tiles that
+    ///     differ between the two: nodes are added or removed where the walkability changed and the edges
+    ///     around every changed tile are weighted again, the rest of the graph is left as it is. The teleport
+    ///     network is rebuilt as well. It is meant to be called at every tick with the robot_map of the
+    ///     previous tick, which is much cheaper than a new `init` when only a few tiles changed.
+    ///     If the two maps have different sizes the whole graph is built again on `new_map`.
+    ///     The graph keeps being built the way it was: the predicate of `init_with_walkability` and the contents
+    ///     of `init_with_content_map` are applied to the changed tiles too. The regions of `init_region` and
+    ///     `init_confirmed` are not remembered instead, a changed tile outside of them becomes a node as with `init`.
+    ///     ***NOTE***: the result is the same graph the last init would build on `new_map`, but the NodeIndex and
+    ///     EdgeIndex of the public fields are not: see `remove_node`. The home set with `set_home` is discarded.
+    ///     ### Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let map = robot_map(world).unwrap();
+    ///             self.charted_path.refresh(&self.last_map, &map, world);
+    ///             self.last_map = map;
+    ///         }
+    pub fn refresh(&mut self, old_map: &Vec<Vec<Option<Tile>>>, new_map: &Vec<Vec<Option<Tile>>>, world: &World) {
+        let dimension = new_map.len();
+        if old_map.len() != dimension
+            || self.indexes.len() != dimension
+            || old_map.iter().zip(new_map.iter()).any(|(a, b)| a.len() != b.len())
+            || self.indexes.iter().zip(new_map.iter()).any(|(a, b)| a.len() != b.len())
+        {
+            self.build(new_map, world);
+            return;
+        }
+
+        let mut changed = Vec::new();
+        for i in 0..dimension {
+            for j in 0..new_map[i].len() {
+                if old_map[i][j] != new_map[i][j] {
+                    changed.push(ChartedCoordinate(i, j));
+                }
+            }
+        }
+        if changed.is_empty() {
+            return;
+        }
+        self.home = None;
+
+        // the teleport network is rebuilt from scratch at the end
+        while let Some(edge) = self.teleports_edges.keys().next().copied() {
+            self.remove_edge(edge);
+        }
+        for coordinate in changed.iter() {
+            let tile = new_map[coordinate.0][coordinate.1].as_ref();
+            self.elevations[coordinate.0][coordinate.1] = tile.map(|t| t.elevation);
+            let _ = self.remove_node(*coordinate);
+            if let Some(tile) = tile.filter(|t| self.is_walkable(t)) {
+                let node = self.graph.add_node(*coordinate);
+                self.indexes[coordinate.0][coordinate.1] = Some(node);
+                if tile.tile_type == TileType::Teleport(true) {
+                    self.teleports.push(*coordinate);
+                }
+            }
+        }
+        for coordinate in changed.iter() {
+            let node = match self.node_at(*coordinate) {
+                | None => continue,
+                | Some(node) => node,
+            };
+            let neighbours = [
+                coordinate.0.checked_sub(1).map(|r| ChartedCoordinate(r, coordinate.1)),
+                Some(ChartedCoordinate(coordinate.0 + 1, coordinate.1)),
+                coordinate.1.checked_sub(1).map(|c| ChartedCoordinate(coordinate.0, c)),
+                Some(ChartedCoordinate(coordinate.0, coordinate.1 + 1)),
+            ];
+            for neighbour in neighbours.into_iter().flatten() {
+                let other = match self.node_at(neighbour) {
+                    | None => continue,
+                    | Some(other) => other,
+                };
+                let weight = self.terrain_weight(coordinate, &neighbour, new_map, world);
+                // two changed neighbours meet twice, update_edge keeps a single edge between them
+                self.graph.update_edge(node, other, weight);
+            }
+        }
+        // the teleports are kept in the order init would find them
+        self.teleports.sort_by_key(|c| (c.0, c.1));
+        self.connect_teleports();
+    }
+
+    ///     Returns a one line description of the graph, handy to be logged at every tick:
+    ///     the size of the map, how many nodes, edges and teleports it contains.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             println!("{}", cp.summary());
+    ///             // ChartedPaths 20x20: 312 nodes, 580 edges, 2 teleports
+    ///         }
+    pub fn summary(&self) -> String {
+        format!(
+            "ChartedPaths {}x{}: {} nodes, {} edges, {} teleports",
+            self.indexes.len(),
+            self.indexes.first().map_or(0, |row| row.len()),
+            self.graph.node_count(),
+            self.graph.edge_count(),
+            self.teleports.len()
+        )
+    }
+
+    ///     Returns the smallest and the biggest weight among the edges of the graph, teleports included,
+    ///     or None if the graph has no edges. Besides helping to tune the heuristics, it works as a diagnostic:
+    ///     a maximum of u32::MAX means that an edge was built between tiles that are not adjacent, which is a bug.
+    pub fn weight_bounds(&self) -> Option<(u32, u32)> {
+        let min = self.graph.edge_weights().copied().min()?;
+        let max = self.graph.edge_weights().copied().max()?;
+        Some((min, max))
     }
 
     ///     Takes as parameter two coordinates, "from" and "to" as ChartedCoordinates.
@@ -206,38 +785,61 @@ impl ChartedPaths {
     ///             let distance=cp.shortest_path_cost(my_coordinate,destination);
     ///         }
     pub fn shortest_path_cost(&self, from: ChartedCoordinate, to: ChartedCoordinate) -> Option<u32> {
-        if ChartedPaths::check_boundaries(self, from, to) == false {
-            return None;
-        }
-        let result = dijkstra(
-            &self.graph,
-            self.indexes[from.0][from.1].unwrap(),
-            self.indexes[to.0][to.1],
-            |e| *e.weight(),
-        );
-        let cost = result.get(&self.indexes[to.0][to.1].unwrap());
-        return match cost {
-            | None => None,
-            | Some(x) => Some(*x),
-        };
+        self.cost(from, to, PathAlgorithm::Dijkstra)
+    }
+
+    ///     Same as shortest_path_cost, but together with the cost it returns the number of steps of the path,
+    ///     to plan against both an energy and a time budget without rebuilding the whole path. A teleport
+    ///     counts as a single step.
+    ///     ***NOTE***: the steps are the ones of the cheapest path, which is not necessarily the one with
+    ///     the fewest steps: a longer detour may cost less energy than a short climb.
+    pub fn shortest_path_cost_and_hops(&self, from: ChartedCoordinate, to: ChartedCoordinate) -> Option<(u32, usize)> {
+        let (start, goal) = (self.node_at(from)?, self.node_at(to)?);
+        astar(&self.graph, start, |finish| finish == goal, |e| *e.weight(), |node| self.heuristic(node, to))
+            .map(|(cost, nodes)| (cost, nodes.len() - 1))
+    }
+
+    ///     Enables or disables the heuristic used by the A* queries (enabled by default).
+    ///
+    ///     The heuristic is the manhattan distance to the destination times the cheapest edge
+    ///     weight of the graph, capped to the cost of a teleport when the graph contains any:
+    ///     it never overestimates, so the paths stay optimal while far fewer tiles are explored.
+    ///     Disabling it makes A* behave exactly like Dijkstra, which is useful to double check results.
+    pub fn set_heuristic(&mut self, enabled: bool) {
+        self.use_heuristic = enabled;
     }
 
     ///     Same as shortest_path_cost but inside it uses the A* algorithm
     pub fn shortest_path_cost_a_star(&self, from: ChartedCoordinate, to: ChartedCoordinate) -> Option<u32> {
+        self.cost(from, to, PathAlgorithm::AStar)
+    }
+
+    ///     Same as shortest_path_cost, but the algorithm used is chosen with the last parameter, so that
+    ///     it can be decided at runtime. Both always return the same cost: A* usually explores fewer tiles
+    ///     (see `set_heuristic`), while Dijkstra has no heuristic to evaluate.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let algorithm = if self.far_away { PathAlgorithm::AStar } else { PathAlgorithm::Dijkstra };
+    ///             let distance = cp.cost(my_coordinate, ChartedCoordinate(1, 2), algorithm);
+    ///         }
+    pub fn cost(&self, from: ChartedCoordinate, to: ChartedCoordinate, algo: PathAlgorithm) -> Option<u32> {
         if ChartedPaths::check_boundaries(self, from, to) == false {
             return None;
         }
-        let path_info = astar(
-            &self.graph,
-            self.indexes[from.0][from.1].unwrap(),
-            |finish| finish == self.indexes[to.0][to.1].unwrap(),
-            |e| *e.weight(),
-            |_| 0,
-        );
-        return match path_info {
-            | None => None,
-            | Some(info) => Some(info.0),
-        };
+        let (start, finish) = (self.indexes[from.0][from.1].unwrap(), self.indexes[to.0][to.1].unwrap());
+        match algo {
+            | PathAlgorithm::Dijkstra => {
+                let result = dijkstra(&self.graph, start, Some(finish), |e| *e.weight());
+                result.get(&finish).copied()
+            }
+            | PathAlgorithm::AStar => {
+                let path_info =
+                    astar(&self.graph, start, |node| node == finish, |e| *e.weight(), |node| self.heuristic(node, to));
+                path_info.map(|info| info.0)
+            }
+        }
     }
 
     ///    Takes as parameter two coordinates, "from" and "to".
@@ -251,6 +853,9 @@ impl ChartedPaths {
     ///    ***NOTE***: as said in the introduction the entire structure and functions works on the
     ///    discovered tiles, so both coordinates passed to the function must be in the robot_map passed
     ///    in the function initialization.
+    ///    When several paths have the same minimum cost, the one returned is always the same: at every step
+    ///    the next tile is the smallest one (by row, then by column) from which `to` can still be reached at
+    ///    the minimum cost, as with `shortest_path_tie_break` and `TieBreak::SmallestCoordinates`.
     ///    ## Example:
     ///
     ///         fn process_tick(& mut self, world:&mut World){
@@ -279,38 +884,272 @@ impl ChartedPaths {
         from: ChartedCoordinate,
         to: ChartedCoordinate,
     ) -> Option<(usize, Vec<ChartedCoordinate>)> {
-        if ChartedPaths::check_boundaries(self, from, to) == false {
-            return None;
+        let (start, goal) = (self.node_at(from)?, self.node_at(to)?);
+        // the search goes from `to` back to `from`, so that the distances to `to` needed to pick the path are known
+        let to_goal = self.distances_to(goal, start)?;
+        let path = self.walk_cheapest(start, goal, &to_goal)?;
+        Some((to_goal[&start] as usize, path))
+    }
+
+    ///     Same as shortest_path, but the path is returned as the list of moves needed to follow it: walking to
+    ///     an adjacent tile becomes a `PathInstruction::Step` with its direction, while the jumps between
+    ///     teleports, which a Direction cannot express, become `PathInstruction::Teleport`. Two adjacent
+    ///     teleports give a Teleport only if teleporting is cheaper than walking between them.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             if let Some((cost, plan)) = cp.shortest_path_instructions(my_coordinate, ChartedCoordinate(1, 2)) {
+    ///                 for instruction in plan {
+    ///                     match instruction {
+    ///                         | PathInstruction::Step(direction) => { go(self, world, direction); }
+    ///                         | PathInstruction::Teleport { from, to } => { /* use the teleport */ }
+    ///                     }
+    ///                 }
+    ///             }
+    ///         }
+    pub fn shortest_path_instructions(
+        &self,
+        from: ChartedCoordinate,
+        to: ChartedCoordinate,
+    ) -> Option<(u32, Vec<PathInstruction>)> {
+        let (cost, path) = self.shortest_path(from, to)?;
+        let mut instructions = Vec::with_capacity(path.len().saturating_sub(1));
+        for step in path.windows(2) {
+            let (a, b) = (self.node_at(step[0])?, self.node_at(step[1])?);
+            // the edge a path would use is the cheapest one between the two tiles
+            let teleport = self
+                .graph
+                .edges_connecting(a, b)
+                .min_by_key(|e| *e.weight())
+                .map_or(false, |e| self.teleports_edges.contains_key(&e.id()));
+            if teleport {
+                instructions.push(PathInstruction::Teleport { from: step[0], to: step[1] });
+            } else {
+                let direction = ChartedPaths::coordinates_to_direction(step[0], step[1]).ok()?;
+                instructions.push(PathInstruction::Step(direction));
+            }
         }
+        Some((cost as u32, instructions))
+    }
 
-        let path_info = astar(
-            &self.graph,
-            self.indexes[from.0][from.1].unwrap(),
-            |finish| finish == self.indexes[to.0][to.1].unwrap(),
-            |e| *e.weight(),
-            |_| 0,
-        );
+    ///     Same as shortest_path, but when several paths have the same minimum cost the choice among them is
+    ///     deterministic, following the provided TieBreak, so that the robot always takes the same route on
+    ///     the same graph. Two searches are run (one from each end), plus a walk over the tiles that lie on
+    ///     a cheapest path.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let path = cp.shortest_path_tie_break(my_coordinate, ChartedCoordinate(1, 2), TieBreak::FewestHops);
+    ///         }
+    pub fn shortest_path_tie_break(
+        &self,
+        from: ChartedCoordinate,
+        to: ChartedCoordinate,
+        tie_break: TieBreak,
+    ) -> Option<(usize, Vec<ChartedCoordinate>)> {
+        let (start, goal) = (self.node_at(from)?, self.node_at(to)?);
+        let (from_start, _) = self.dijkstra_tree(&[start]);
+        let (to_goal, _) = self.dijkstra_tree(&[goal]);
+        let best = *from_start.get(&goal)?;
+        // the edge from a to b is on a cheapest path if going through it costs exactly the best cost
+        let optimal = |a: NodeIndex, b: NodeIndex, weight: u32| match (from_start.get(&a), to_goal.get(&b)) {
+            | (Some(x), Some(y)) => x.saturating_add(weight).saturating_add(*y) == best,
+            | _ => false,
+        };
 
-        return match path_info {
-            | None => None,
-            | Some(a_star_result) => {
-                let cost = a_star_result.0 as usize;
-                let nodes = a_star_result.1;
+        // steps left to the goal along cheapest paths, only needed to prefer the shortest ones
+        let mut hops_left: HashMap<NodeIndex, usize> = HashMap::new();
+        if tie_break == TieBreak::FewestHops {
+            hops_left.insert(goal, 0);
+            let mut queue = VecDeque::from([goal]);
+            while let Some(node) = queue.pop_front() {
+                let hops = hops_left[&node];
+                for edge in self.graph.edges(node) {
+                    let previous = if edge.source() == node { edge.target() } else { edge.source() };
+                    if !hops_left.contains_key(&previous) && optimal(previous, node, *edge.weight()) {
+                        hops_left.insert(previous, hops + 1);
+                        queue.push_back(previous);
+                    }
+                }
+            }
+        }
 
-                let mut path = Vec::new();
+        let path = self.walk_smallest(start, goal, |node, next, weight| {
+            optimal(node, next, weight)
+                && match tie_break {
+                    | TieBreak::FewestHops => hops_left.get(&next).map(|h| h + 1) == hops_left.get(&node).copied(),
+                    | TieBreak::SmallestCoordinates => true,
+                }
+        })?;
+        Some((best as usize, path))
+    }
 
-                for i in nodes.iter() {
-                    let converted = ChartedPaths::index_to_coordinate(self, i);
-                    match converted {
-                        | None => {}
-                        | Some(x) => {
-                            path.push(x);
-                        }
+    ///     Same as shortest_path, but the path found is the cheapest one whose total climbing, the sum of
+    ///     the elevation gained at every uphill step, is at most `max_total_gain`; descents do not give back
+    ///     any climbing and teleports climb nothing. It is meant for robots that can only climb so much.
+    ///     Returns None if no path respects the limit.
+    ///     ***NOTE***: since both the cost and the climbing have to be tracked, the search keeps several
+    ///     candidate paths per tile and is slower than shortest_path.
+    pub fn shortest_path_max_climb(
+        &self,
+        from: ChartedCoordinate,
+        to: ChartedCoordinate,
+        max_total_gain: u32,
+    ) -> Option<(u32, Vec<ChartedCoordinate>)> {
+        let (start, goal) = (self.node_at(from)?, self.node_at(to)?);
+        // every label is a partial path: (node, cost, climbing, previous label, still useful)
+        let mut labels: Vec<(NodeIndex, u32, u32, Option<usize>, bool)> = vec![(start, 0, 0, None, true)];
+        let mut at_node: HashMap<NodeIndex, Vec<usize>> = HashMap::from([(start, vec![0])]);
+        let mut queue = BinaryHeap::from([Reverse((0u32, 0u32, 0usize))]);
+
+        while let Some(Reverse((cost, gain, label))) = queue.pop() {
+            let node = labels[label].0;
+            if !labels[label].4 {
+                // dominated by a better path found later
+                continue;
+            }
+            if node == goal {
+                let mut path = Vec::new();
+                let mut current = Some(label);
+                while let Some(l) = current {
+                    path.push(self.graph[labels[l].0]);
+                    current = labels[l].3;
+                }
+                path.reverse();
+                return Some((cost, path));
+            }
+            for edge in self.graph.edges(node) {
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                let climb = if self.teleports_edges.contains_key(&edge.id()) {
+                    0
+                } else {
+                    self.elevation_of(self.graph[next]).saturating_sub(self.elevation_of(self.graph[node])) as u32
+                };
+                let (next_cost, next_gain) = (cost.saturating_add(*edge.weight()), gain.saturating_add(climb));
+                if next_gain > max_total_gain {
+                    continue;
+                }
+                let others = at_node.entry(next).or_default();
+                if others.iter().any(|o| labels[*o].1 <= next_cost && labels[*o].2 <= next_gain) {
+                    continue;
+                }
+                others.retain(|o| {
+                    let dominated = next_cost <= labels[*o].1 && next_gain <= labels[*o].2;
+                    if dominated {
+                        labels[*o].4 = false;
                     }
+                    !dominated
+                });
+                others.push(labels.len());
+                queue.push(Reverse((next_cost, next_gain, labels.len())));
+                labels.push((next, next_cost, next_gain, Some(label), true));
+            }
+        }
+        None
+    }
+
+    ///     Same as shortest_path, but teleports are never used: the path stays on the physical terrain,
+    ///     for example for a robot that has no access to the teleport network. Teleport tiles can still
+    ///     be walked over as any other tile. The other queries keep using teleports.
+    pub fn shortest_path_no_teleport(
+        &self,
+        from: ChartedCoordinate,
+        to: ChartedCoordinate,
+    ) -> Option<(u32, Vec<ChartedCoordinate>)> {
+        let (start, goal) = (self.node_at(from)?, self.node_at(to)?);
+        let terrain = EdgeFiltered::from_fn(&self.graph, |e| !self.teleports_edges.contains_key(&e.id()));
+        let (cost, nodes) =
+            astar(&terrain, start, |node| node == goal, |e| *e.weight(), |node| self.heuristic(node, to))?;
+        Some((cost, nodes.iter().map(|node| self.graph[*node]).collect()))
+    }
+
+    ///     Same as shortest_path, but the search is abandoned as soon as every tile still to be explored
+    ///     costs more than `max_cost` to reach: it returns None if there is no path costing at most `max_cost`,
+    ///     without exploring the rest of the map. If such a path exists, it is the optimal one.
+    ///     It bounds the time spent on hopeless or very far queries, when a quick "no" is better than
+    ///     a slow answer.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let energy = self.get_energy().get_energy_level() as u32;
+    ///             match cp.shortest_path_capped(my_coordinate, goal, energy) {
+    ///                 | None => {} // not affordable this tick
+    ///                 | Some((cost, path)) => {} // walk along path
+    ///             }
+    ///         }
+    pub fn shortest_path_capped(
+        &self,
+        from: ChartedCoordinate,
+        to: ChartedCoordinate,
+        max_cost: u32,
+    ) -> Option<(u32, Vec<ChartedCoordinate>)> {
+        let (start, goal) = (self.node_at(from)?, self.node_at(to)?);
+        let mut costs: HashMap<NodeIndex, u32> = HashMap::from([(start, 0)]);
+        let mut predecessors: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut queue = BinaryHeap::from([Reverse((0u32, start))]);
+        while let Some(Reverse((cost, node))) = queue.pop() {
+            if cost > max_cost {
+                // every other tile in the queue is at least as expensive
+                return None;
+            }
+            if node == goal {
+                let mut path = vec![self.graph[node]];
+                let mut current = node;
+                while let Some(previous) = predecessors.get(&current) {
+                    path.push(self.graph[*previous]);
+                    current = *previous;
                 }
-                Some((cost, path))
+                path.reverse();
+                return Some((cost, path));
             }
-        };
+            if costs.get(&node).map_or(false, |best| cost > *best) {
+                continue;
+            }
+            for edge in self.graph.edges(node) {
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                let next_cost = cost.saturating_add(*edge.weight());
+                if next_cost <= max_cost && costs.get(&next).map_or(true, |best| next_cost < *best) {
+                    costs.insert(next, next_cost);
+                    predecessors.insert(next, node);
+                    queue.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        None
+    }
+
+    ///     Takes as parameter two adjacent coordinates and returns the energy needed to step from the first
+    ///     to the second right now, with the current environmental conditions, without building any graph.
+    ///     Unlike the edges of the graph, which hold the costlier of the two directions, the cost is the one
+    ///     of this direction only: climbing adds the square of the elevation difference, descending adds nothing.
+    ///     Returns None if the coordinates are not adjacent, out of the map, or if either tile is undiscovered
+    ///     or not walkable.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let north = ChartedCoordinate(my_coordinate.0 - 1, my_coordinate.1);
+    ///             let cost = ChartedPaths::step_cost(my_coordinate, north, &robot_map(world).unwrap(), world);
+    ///         }
+    pub fn step_cost(
+        from: ChartedCoordinate,
+        to: ChartedCoordinate,
+        map: &Vec<Vec<Option<Tile>>>,
+        world: &World,
+    ) -> Option<u32> {
+        if !ChartedCoordinate::is_close_to(&from, &to) || from == to {
+            return None;
+        }
+        for c in [from, to] {
+            let tile = map.get(c.0)?.get(c.1)?.as_ref()?;
+            if !tile.tile_type.properties().walk() {
+                return None;
+            }
+        }
+        Some(ChartedPaths::eval_weight(&from, &to, map, world, false, &HashMap::new()))
     }
 
     /// 5) `pub fn coordinates_to_direction(ChartedCoordinate, ChartedCoordinate) -> Result<Direction, ()>`
@@ -354,15 +1193,1421 @@ impl ChartedPaths {
         return Err(());
     }
 
-    fn check_boundaries(&self, from: ChartedCoordinate, to: ChartedCoordinate) -> bool {
-        let x= self.indexes[from.0][from.1];
-        let y= self.indexes[to.0][to.1];
-        return x.is_some() && y.is_some();
+    ///     Removes the coordinate from the graph, together with all its edges (teleport ones included),
+    ///     for example when a tile becomes impassable because it caught fire, without having to call
+    ///     `init` again. Returns Err if the coordinate is not in the graph.
+    ///     ***NOTE***: petgraph moves the last node and edges into the freed indexes, so any NodeIndex or
+    ///     EdgeIndex obtained from the public fields before the removal may now point elsewhere;
+    ///     `indexes` and `teleports_edges` are kept up to date.
+    pub fn remove_node(&mut self, coordinate: ChartedCoordinate) -> Result<(), ()> {
+        let node = self.node_at(coordinate).ok_or(())?;
+        loop {
+            let edge = match self.graph.edges(node).next() {
+                | None => break,
+                | Some(edge) => edge.id(),
+            };
+            self.remove_edge(edge);
+        }
+
+        let last = NodeIndex::new(self.graph.node_count() - 1);
+        self.graph.remove_node(node);
+        self.indexes[coordinate.0][coordinate.1] = None;
+        if last != node {
+            // the last node took the place of the removed one
+            let moved = self.graph[node];
+            self.indexes[moved.0][moved.1] = Some(node);
+        }
+        self.teleports.retain(|teleport| *teleport != coordinate);
+        Ok(())
     }
 
-    fn index_to_coordinate(&self, node_index: &NodeIndex) -> Option<ChartedCoordinate> {
-        let dim = self.indexes.len();
-        for i in 0..dim {
+    ///     Returns true if the coordinate is part of the graph, meaning that the tile has been discovered
+    ///     and is walkable. Coordinates outside the map simply return false, so it can be used to
+    ///     validate the endpoints of a query before running it.
+    pub fn has_node(&self, coordinate: ChartedCoordinate) -> bool {
+        self.node_at(coordinate).is_some()
+    }
+
+    ///     The graph is a snapshot of the robot_map taken by `init`, by the time a stored path is followed
+    ///     the world may have changed. This function checks the path against the current robot_map: every
+    ///     coordinate has to be discovered and walkable, and every step has to move to an adjacent tile (or
+    ///     jump between two working teleports). Returns the first coordinate where the path is no longer
+    ///     valid, so a stale plan can be detected before committing any move.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             if let Err(blocked) = cp.validate_path(&self.plan, world) {
+    ///                 // re-plan, the path is broken at `blocked`
+    ///             }
+    ///         }
+    pub fn validate_path(&self, path: &[ChartedCoordinate], world: &World) -> Result<(), ChartedCoordinate> {
+        let map = match robot_map(world) {
+            | None => return path.first().map_or(Ok(()), |first| Err(*first)),
+            | Some(map) => map,
+        };
+        let tile_at = |coordinate: &ChartedCoordinate| -> Option<Tile> {
+            map.get(coordinate.0).and_then(|row| row.get(coordinate.1).cloned().flatten())
+        };
+        for (index, coordinate) in path.iter().enumerate() {
+            let tile = match tile_at(coordinate) {
+                | Some(tile) if tile.tile_type.properties().walk() => tile,
+                | _ => return Err(*coordinate),
+            };
+            if index == 0 {
+                continue;
+            }
+            let previous = path[index - 1];
+            let adjacent = ChartedCoordinate::manhattan_distance(&previous, coordinate) == 1;
+            let teleport = tile.tile_type == TileType::Teleport(true)
+                && tile_at(&previous).map_or(false, |t| t.tile_type == TileType::Teleport(true));
+            if !adjacent && !teleport {
+                return Err(*coordinate);
+            }
+        }
+        Ok(())
+    }
+
+    ///     Same as shortest_path, but if `to` is not in the graph (it is not walkable, like a water tile
+    ///     the robot wants to stand next to, or not discovered) the path leads to the walkable tile closest
+    ///     to it, by manhattan distance, among the ones reachable from `from`; on equal distance the cheapest
+    ///     one to reach wins. The last coordinate of the path tells where the robot will end up.
+    ///     Returns None only if `from` is not in the graph.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let lake = ChartedCoordinate(4, 7);
+    ///             if let Some((cost, path)) = cp.shortest_path_to_nearest_walkable(my_coordinate, lake) {
+    ///                 // walk along path, then interact with the lake
+    ///             }
+    ///         }
+    pub fn shortest_path_to_nearest_walkable(
+        &self,
+        from: ChartedCoordinate,
+        to: ChartedCoordinate,
+    ) -> Option<(u32, Vec<ChartedCoordinate>)> {
+        if self.node_at(to).is_some() {
+            if let Some((cost, path)) = self.shortest_path(from, to) {
+                return Some((cost as u32, path));
+            }
+        }
+        let start = self.node_at(from)?;
+        let costs = dijkstra(&self.graph, start, None, |e| *e.weight());
+        let target = costs
+            .iter()
+            .map(|(node, cost)| (self.graph[*node], *cost))
+            .min_by_key(|(c, cost)| (ChartedCoordinate::manhattan_distance(c, &to), *cost, c.0, c.1))?
+            .0;
+        self.shortest_path(from, target).map(|(cost, path)| (cost as u32, path))
+    }
+
+    ///     Takes as parameter the starting coordinate and a list of candidate destinations, for example
+    ///     the coordinates returned by `ChartedMap::get`.
+    ///     Returns the candidate that is cheapest to reach, together with the cost and the path to it
+    ///     (as in `shortest_path`). Candidates that are not reachable are ignored, if none of them is
+    ///     reachable it returns None. On equal costs the first candidate in the list wins.
+    ///     It runs a single Dijkstra from `from` instead of one search per candidate.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let water: Vec<ChartedCoordinate> = charted_map.get(&Content::Water(0))
+    ///                 .map(|v| v.iter().map(|(c, _)| *c).collect())
+    ///                 .unwrap_or_default();
+    ///             if let Some((target, cost, path)) = cp.nearest_reachable(my_coordinate, &water) {
+    ///                 // walk along path
+    ///             }
+    ///         }
+    pub fn nearest_reachable(
+        &self,
+        from: ChartedCoordinate,
+        candidates: &[ChartedCoordinate],
+    ) -> Option<(ChartedCoordinate, u32, Vec<ChartedCoordinate>)> {
+        let start = self.node_at(from)?;
+        let costs = dijkstra(&self.graph, start, None, |e| *e.weight());
+
+        let mut best: Option<(ChartedCoordinate, u32)> = None;
+        for candidate in candidates.iter() {
+            let cost = match self.node_at(*candidate).and_then(|node| costs.get(&node)) {
+                | None => continue,
+                | Some(cost) => *cost,
+            };
+            match best {
+                | Some((_, best_cost)) if best_cost <= cost => {}
+                | _ => best = Some((*candidate, cost)),
+            }
+        }
+
+        let (target, cost) = best?;
+        let (_, path) = self.shortest_path(from, target)?;
+        Some((target, cost, path))
+    }
+
+    ///     Takes as parameter the starting coordinate, a map (like the robot_map passed to `init`) and a tile
+    ///     type, and returns the reachable tile of that type that is cheapest to reach, with the cost and the
+    ///     path to it, or None if no such tile can be reached: "get me to the nearest Sand tile".
+    ///     Only the tiles of the graph are considered, so not walkable types are never found.
+    ///     On equal costs the first tile in row order wins.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let map = robot_map(world).unwrap();
+    ///             if let Some((sand, cost, path)) = cp.cheapest_reachable_type(my_coordinate, &map, TileType::Sand) {
+    ///                 // walk along path
+    ///             }
+    ///         }
+    pub fn cheapest_reachable_type(
+        &self,
+        from: ChartedCoordinate,
+        map: &Vec<Vec<Option<Tile>>>,
+        tile_type: TileType,
+    ) -> Option<(ChartedCoordinate, u32, Vec<ChartedCoordinate>)> {
+        let mut candidates = Vec::new();
+        for (i, row) in map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                if tile.as_ref().map_or(false, |t| t.tile_type == tile_type) && self.has_node(ChartedCoordinate(i, j)) {
+                    candidates.push(ChartedCoordinate(i, j));
+                }
+            }
+        }
+        self.nearest_reachable(from, &candidates)
+    }
+
+    ///     Returns the coordinates of all the working teleports (`TileType::Teleport(true)`) in the graph,
+    ///     in the order they were found during `init`, to reason about the teleport network directly.
+    ///     Every pair of them is connected by an edge costing 30 energy.
+    pub fn teleports(&self) -> Vec<ChartedCoordinate> {
+        self.teleports.clone()
+    }
+
+    ///     Takes as parameter a list of possible starting coordinates and a list of possible destinations,
+    ///     and returns the pair (start, destination) connected by the cheapest path, together with its cost
+    ///     and the path itself (as in shortest_path), or None if no destination is reachable from any start.
+    ///     It runs a single Dijkstra from all the starts at the same time, stopping at the first destination
+    ///     reached, instead of one search per pair: "which warehouse should serve which customer".
+    pub fn cheapest_pair(
+        &self,
+        froms: &[ChartedCoordinate],
+        tos: &[ChartedCoordinate],
+    ) -> Option<(ChartedCoordinate, ChartedCoordinate, u32, Vec<ChartedCoordinate>)> {
+        let targets: HashSet<NodeIndex> = tos.iter().filter_map(|c| self.node_at(*c)).collect();
+        let mut costs: HashMap<NodeIndex, u32> = HashMap::new();
+        let mut predecessors: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        for source in froms.iter().filter_map(|c| self.node_at(*c)) {
+            if !costs.contains_key(&source) {
+                costs.insert(source, 0);
+                queue.push(Reverse((0u32, source)));
+            }
+        }
+
+        while let Some(Reverse((cost, node))) = queue.pop() {
+            if costs.get(&node).map_or(false, |best| cost > *best) {
+                continue;
+            }
+            if targets.contains(&node) {
+                let mut path = vec![self.graph[node]];
+                let mut current = node;
+                while let Some(previous) = predecessors.get(&current) {
+                    path.push(self.graph[*previous]);
+                    current = *previous;
+                }
+                path.reverse();
+                return Some((path[0], self.graph[node], cost, path));
+            }
+            for edge in self.graph.edges(node) {
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                let next_cost = cost.saturating_add(*edge.weight());
+                if costs.get(&next).map_or(true, |best| next_cost < *best) {
+                    costs.insert(next, next_cost);
+                    predecessors.insert(next, node);
+                    queue.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        None
+    }
+
+    ///     Takes as parameter the starting coordinate and returns the coordinates of every node of the graph
+    ///     that cannot be reached from it, teleports taken into account, for example a small island the robot
+    ///     can see but cannot walk to. Checking a destination against it avoids running doomed searches.
+    ///     The coordinates are in graph order; if `from` is not in the graph, no node is reachable and all
+    ///     of them are returned.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let islands = cp.unreachable_from(my_coordinate);
+    ///         }
+    pub fn unreachable_from(&self, from: ChartedCoordinate) -> Vec<ChartedCoordinate> {
+        let mut reached = HashSet::new();
+        if let Some(start) = self.node_at(from) {
+            let mut bfs = Bfs::new(&self.graph, start);
+            while let Some(node) = bfs.next(&self.graph) {
+                reached.insert(node);
+            }
+        }
+        self.graph.node_indices().filter(|node| !reached.contains(node)).map(|node| self.graph[node]).collect()
+    }
+
+    ///     Sets the home of the robot, the place it has to be able to go back to, and computes once the cost
+    ///     to reach it from every node of the graph, so that every following `cost_home` is a simple lookup
+    ///     instead of a new search. Returns Err if `home` is not in the graph.
+    ///     ***NOTE***: the costs are computed on the current graph, they are discarded by `init` and are not
+    ///     updated by `remove_node` or `set_edge_weight`: call `set_home` again after changing the graph.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             cp.init(&robot_map(world).unwrap(), world);
+    ///             cp.set_home(self.base).unwrap();
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let energy = self.get_energy().get_energy_level() as u32;
+    ///             if cp.cost_home(my_coordinate).map_or(true, |cost| cost + 50 > energy) {
+    ///                 // time to go back
+    ///             }
+    ///         }
+    pub fn set_home(&mut self, home: ChartedCoordinate) -> Result<(), ()> {
+        let start = self.node_at(home).ok_or(())?;
+        // the graph is undirected, so the costs from home are also the costs to go back home
+        let costs = dijkstra(&self.graph, start, None, |e| *e.weight())
+            .into_iter()
+            .map(|(node, cost)| (self.graph[node], cost))
+            .collect();
+        self.home = Some((home, costs));
+        Ok(())
+    }
+
+    ///     Returns the cost of the cheapest path from `from` back to the home set with `set_home`,
+    ///     or None if no home has been set or if it cannot be reached from `from`.
+    pub fn cost_home(&self, from: ChartedCoordinate) -> Option<u32> {
+        self.home.as_ref().and_then(|(_, costs)| costs.get(&from).copied())
+    }
+
+    ///     Takes as parameter the starting coordinate, a ChartedMap and a content, and among the coordinates
+    ///     where that content has been saved returns the one that gives the most for the energy spent: the one
+    ///     with the highest saved quantity divided by the cost of reaching it. The coordinate is returned with
+    ///     that ratio and the path to it; unreachable coordinates are skipped, and on equal ratio the one
+    ///     saved first wins. A deposit the robot is standing on counts as costing 1, so that the ratio stays
+    ///     finite. Returns None if no coordinate of that content can be reached.
+    ///     It runs a single Dijkstra from `from` instead of one search per deposit.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let best = cp.best_value_target(my_coordinate, &self.contents, &Content::Coin(0));
+    ///             if let Some((coin, ratio, path)) = best {
+    ///                 // follow path to the best deposit
+    ///             }
+    ///         }
+    pub fn best_value_target(
+        &self,
+        from: ChartedCoordinate,
+        map: &ChartedMap<Content>,
+        content: &Content,
+    ) -> Option<(ChartedCoordinate, f64, Vec<ChartedCoordinate>)> {
+        let start = self.node_at(from)?;
+        let (costs, predecessors) = self.dijkstra_tree(&[start]);
+
+        let mut best: Option<(NodeIndex, f64)> = None;
+        for (coordinate, quantity) in map.get(content)?.iter() {
+            let node = match self.node_at(*coordinate) {
+                | None => continue,
+                | Some(node) => node,
+            };
+            let cost = match costs.get(&node) {
+                | None => continue,
+                | Some(cost) => (*cost).max(1),
+            };
+            let ratio = quantity.as_usize().unwrap_or(0) as f64 / cost as f64;
+            if best.map_or(true, |(_, best_ratio)| ratio > best_ratio) {
+                best = Some((node, ratio));
+            }
+        }
+
+        let (node, ratio) = best?;
+        let mut path = vec![self.graph[node]];
+        let mut current = node;
+        while let Some(previous) = predecessors.get(&current) {
+            path.push(self.graph[*previous]);
+            current = *previous;
+        }
+        path.reverse();
+        Some((self.graph[node], ratio, path))
+    }
+
+    ///     Takes as parameter the starting coordinate and a ChartedMap, and returns for every content saved in it
+    ///     the sum of the quantities saved at the coordinates that can be reached from `from`, for example
+    ///     40 reachable coins and 0 reachable fish. Contents with nothing reachable are present with 0; if
+    ///     `from` is not in the graph, every total is 0. It runs a single Dijkstra from `from`.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             for (content, total) in cp.reachable_content_totals(my_coordinate, &self.contents) {
+    ///                 println!("{:?}: {}", content, total);
+    ///             }
+    ///         }
+    pub fn reachable_content_totals(&self, from: ChartedCoordinate, map: &ChartedMap<Content>) -> HashMap<Content, usize> {
+        let costs = match self.node_at(from) {
+            | None => HashMap::new(),
+            | Some(start) => dijkstra(&self.graph, start, None, |e| *e.weight()),
+        };
+        map.iter()
+            .map(|(content, entries)| {
+                let total = entries
+                    .iter()
+                    .filter(|(c, _)| self.node_at(*c).map_or(false, |node| costs.contains_key(&node)))
+                    .map(|(_, q)| q.as_usize().unwrap_or(0))
+                    .sum();
+                (content.clone(), total)
+            })
+            .collect()
+    }
+
+    ///     Takes as parameter the starting coordinate and returns the working teleport that is cheapest
+    ///     to reach, together with the cost and the path to it, or None if no teleport is reachable.
+    ///     If `from` is itself a teleport, it is returned with cost 0.
+    pub fn nearest_teleport(&self, from: ChartedCoordinate) -> Option<(ChartedCoordinate, u32, Vec<ChartedCoordinate>)> {
+        self.nearest_reachable(from, &self.teleports)
+    }
+
+    ///     Takes as parameter the starting coordinate and a list of candidate destinations, for example
+    ///     the coordinates saved in a ChartedMap, and returns only the candidates that can be reached from
+    ///     `from`, each with the cost of the shortest path to it, sorted by ascending cost (candidates with
+    ///     the same cost keep the order of the list). Duplicated candidates are returned only once.
+    ///     It runs a single Dijkstra from `from` instead of one search per candidate.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let trees: Vec<ChartedCoordinate> = charted_map.get(&Content::Tree(0))
+    ///                 .map(|v| v.iter().map(|(c, _)| *c).collect())
+    ///                 .unwrap_or_default();
+    ///             for (tree, cost) in cp.filter_reachable(my_coordinate, &trees) {
+    ///                 // the closest trees come first
+    ///             }
+    ///         }
+    pub fn filter_reachable(
+        &self,
+        from: ChartedCoordinate,
+        candidates: &[ChartedCoordinate],
+    ) -> Vec<(ChartedCoordinate, u32)> {
+        let start = match self.node_at(from) {
+            | None => return Vec::new(),
+            | Some(node) => node,
+        };
+        let costs = dijkstra(&self.graph, start, None, |e| *e.weight());
+
+        let mut seen = HashSet::new();
+        let mut reachable: Vec<(ChartedCoordinate, u32)> = candidates
+            .iter()
+            .filter(|candidate| seen.insert(**candidate))
+            .filter_map(|candidate| {
+                let node = self.node_at(*candidate)?;
+                costs.get(&node).map(|cost| (*candidate, *cost))
+            })
+            .collect();
+        reachable.sort_by_key(|(_, cost)| *cost);
+        reachable
+    }
+
+    ///     Same as filter_reachable, meant for the frontier of the explored area returned by
+    ///     `ChartedWorld::frontier`: the result tells which frontier tiles can be reached and how much it
+    ///     costs, cheapest first, answering "where should I explore next".
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let frontier = charted_world.frontier();
+    ///             if let Some((next, cost)) = cp.reachable_frontier(my_coordinate, &frontier).first() {
+    ///                 // go to next and discover around it
+    ///             }
+    ///         }
+    pub fn reachable_frontier(
+        &self,
+        from: ChartedCoordinate,
+        frontier: &[ChartedCoordinate],
+    ) -> Vec<(ChartedCoordinate, u32)> {
+        self.filter_reachable(from, frontier)
+    }
+
+    ///     Takes as parameter the starting coordinate and a list of targets, for example tiles the robot
+    ///     wants to discover, and returns an order in which to visit them that keeps the total energy low:
+    ///     starting from `from`, the cheapest target to reach from the current position is always the next one.
+    ///     ***NOTE***: it is the nearest-neighbour heuristic, not an exact solution of the travelling
+    ///     salesman problem, so the order is good but not guaranteed to be the optimal one.
+    ///     Targets that are not reachable are left out, duplicated ones appear only once, and on equal
+    ///     costs the target that comes first in the list wins.
+    ///     It runs one Dijkstra per target.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let order = cp.plan_visit_order(my_coordinate, &targets);
+    ///             let mut position = my_coordinate;
+    ///             for target in order {
+    ///                 let (_, path) = cp.shortest_path(position, target).unwrap();
+    ///                 // walk along path, then discover around target
+    ///                 position = target;
+    ///             }
+    ///         }
+    pub fn plan_visit_order(&self, from: ChartedCoordinate, targets: &[ChartedCoordinate]) -> Vec<ChartedCoordinate> {
+        self.visit_order_with_costs(from, targets).into_iter().map(|(target, _)| target).collect()
+    }
+
+    fn visit_order_with_costs(
+        &self,
+        from: ChartedCoordinate,
+        targets: &[ChartedCoordinate],
+    ) -> Vec<(ChartedCoordinate, u32)> {
+        // the order of plan_visit_order, together with the cost of reaching every target from the previous one
+        let mut order = Vec::new();
+        let mut seen = HashSet::new();
+        let mut left: Vec<(ChartedCoordinate, NodeIndex)> = targets
+            .iter()
+            .filter(|target| seen.insert(**target))
+            .filter_map(|target| self.node_at(*target).map(|node| (*target, node)))
+            .collect();
+        let mut current = match self.node_at(from) {
+            | None => return order,
+            | Some(node) => node,
+        };
+
+        while !left.is_empty() {
+            let costs = dijkstra(&self.graph, current, None, |e| *e.weight());
+            let mut best: Option<(usize, u32)> = None;
+            for (i, (_, node)) in left.iter().enumerate() {
+                if let Some(cost) = costs.get(node) {
+                    match best {
+                        | Some((_, best_cost)) if best_cost <= *cost => {}
+                        | _ => best = Some((i, *cost)),
+                    }
+                }
+            }
+            match best {
+                // every remaining target is unreachable
+                | None => break,
+                | Some((i, cost)) => {
+                    let (target, node) = left.remove(i);
+                    order.push((target, cost));
+                    current = node;
+                }
+            }
+        }
+        order
+    }
+
+    ///     Takes as parameter a list of coordinates and splits them in groups of coordinates that can reach
+    ///     each other, for example to tell which saved targets are on the same island of the robot and which
+    ///     ones are across water it cannot cross. Teleports are taken into account.
+    ///     Groups are in order of first appearance in the list, and each group keeps the order of the list;
+    ///     coordinates that are not in the graph (not walkable or undiscovered) form a group on their own.
+    ///     The connected components are computed with a single union-find pass over the edges of the graph,
+    ///     so the answer is always up to date, even after `remove_node`.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let mut targets = vec![my_coordinate];
+    ///             targets.extend(charted_map.coordinates_of(&Content::Coin(0)));
+    ///             let groups = cp.partition_by_component(&targets);
+    ///             // groups[0] contains the robot and all the coins it can reach
+    ///         }
+    pub fn partition_by_component(&self, coords: &[ChartedCoordinate]) -> Vec<Vec<ChartedCoordinate>> {
+        let mut components = UnionFind::<usize>::new(self.graph.node_count());
+        for edge in self.graph.raw_edges().iter() {
+            components.union(edge.source().index(), edge.target().index());
+        }
+
+        let mut groups: Vec<Vec<ChartedCoordinate>> = Vec::new();
+        let mut group_of: HashMap<usize, usize> = HashMap::new();
+        for coordinate in coords.iter() {
+            match self.node_at(*coordinate) {
+                | None => groups.push(vec![*coordinate]),
+                | Some(node) => {
+                    let root = components.find(node.index());
+                    match group_of.get(&root) {
+                        | Some(group) => groups[*group].push(*coordinate),
+                        | None => {
+                            group_of.insert(root, groups.len());
+                            groups.push(vec![*coordinate]);
+                        }
+                    }
+                }
+            }
+        }
+        groups
+    }
+
+    ///     Takes as parameter the starting coordinate and a list of points, for example the coins saved in
+    ///     a ChartedMap, and returns an estimate of the energy needed to visit all of them: the total cost
+    ///     of the tour built by `plan_visit_order`. Being a heuristic tour, the optimal one may cost less.
+    ///     Returns None if `from` is not in the graph or if any of the points cannot be reached.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let coins = charted_map.coordinates_of(&Content::Coin(0));
+    ///             let energy = self.get_energy().get_energy_level() as u32;
+    ///             if cp.estimate_tour_cost(my_coordinate, &coins).map_or(false, |cost| cost <= energy) {
+    ///                 // worth collecting all of them
+    ///             }
+    ///         }
+    pub fn estimate_tour_cost(&self, start: ChartedCoordinate, points: &[ChartedCoordinate]) -> Option<u32> {
+        self.node_at(start)?;
+        let order = self.visit_order_with_costs(start, points);
+        if order.len() != points.iter().collect::<HashSet<_>>().len() {
+            // some point was left out, so it is unreachable
+            return None;
+        }
+        Some(order.iter().fold(0u32, |total, (_, cost)| total.saturating_add(*cost)))
+    }
+
+    ///     Takes as parameter the starting coordinate and a list of targets, for example every Garbage tile
+    ///     saved in a ChartedMap, and plans a single route visiting all of them: the targets are visited in
+    ///     the order given by `plan_visit_order` and the shortest paths between them are joined together.
+    ///     Returns the total cost and the full path (starting with `from`), or None if `from` is not in the graph.
+    ///     Targets that are not reachable are skipped: the ones missing from the path were not reachable.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let garbage = charted_map.coordinates_of(&Content::Garbage(0));
+    ///             if let Some((cost, route)) = cp.collect_all_plan(my_coordinate, &garbage) {
+    ///                 // walk along route, destroying the garbage met on the way
+    ///             }
+    ///         }
+    pub fn collect_all_plan(
+        &self,
+        from: ChartedCoordinate,
+        targets: &[ChartedCoordinate],
+    ) -> Option<(u32, Vec<ChartedCoordinate>)> {
+        self.node_at(from)?;
+        let mut total = 0u32;
+        let mut route = vec![from];
+        let mut current = from;
+        for next in self.plan_visit_order(from, targets) {
+            let (cost, path) = self.shortest_path(current, next)?;
+            total = total.saturating_add(cost as u32);
+            // the first tile of every piece is the last one of the route
+            route.extend(path.into_iter().skip(1));
+            current = next;
+        }
+        Some((total, route))
+    }
+
+    ///     Takes as parameter the goal coordinate and runs a single Dijkstra from it, returning
+    ///     for every tile that can reach the goal the direction of the cheapest next step toward it.
+    ///     Many robots heading to the same goal can then follow the field without running any
+    ///     further search: each one just looks up its own coordinate at every step.
+    ///     The goal itself is not present in the field, neither are unreachable tiles.
+    ///     ***NOTE***: teleport edges cannot be expressed as a Direction, so tiles whose cheapest next
+    ///     step is a teleport are not present in the field either.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let field = cp.flow_field(ChartedCoordinate(10, 10));
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             if let Some(direction) = field.get(&my_coordinate) {
+    ///                 go(self, world, direction.clone());
+    ///             }
+    ///         }
+    pub fn flow_field(&self, goal: ChartedCoordinate) -> HashMap<ChartedCoordinate, Direction> {
+        let mut field = HashMap::new();
+        let goal = match self.node_at(goal) {
+            | None => return field,
+            | Some(node) => node,
+        };
+        let (_, predecessors) = self.dijkstra_tree(&[goal]);
+        for (node, next) in predecessors.iter() {
+            let (here, there) = (self.graph[*node], self.graph[*next]);
+            if ChartedCoordinate::is_close_to(&here, &there) {
+                if let Ok(direction) = ChartedPaths::coordinates_to_direction(here, there) {
+                    field.insert(here, direction);
+                }
+            }
+        }
+        field
+    }
+
+    ///     Takes as parameter a field returned by `flow_field` and walks it from `from` until `goal`,
+    ///     returning the directions to follow. Returns None if `from` is not in the field (it cannot reach
+    ///     the goal, or its next step is a teleport) or if the walk gets lost: since each step goes to a tile
+    ///     of the field, the walk is cut after as many steps as the field has tiles, so a field that was not
+    ///     computed for `goal` cannot make it loop forever. If `from` is the goal, the list is empty.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let goal = ChartedCoordinate(10, 10);
+    ///             let field = cp.flow_field(goal);
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             if let Some(directions) = ChartedPaths::follow_flow_field(&field, my_coordinate, goal) {
+    ///                 for direction in directions {
+    ///                     go(self, world, direction);
+    ///                 }
+    ///             }
+    ///         }
+    pub fn follow_flow_field(
+        field: &HashMap<ChartedCoordinate, Direction>,
+        from: ChartedCoordinate,
+        goal: ChartedCoordinate,
+    ) -> Option<Vec<Direction>> {
+        let mut directions = Vec::new();
+        let mut current = from;
+        while current != goal {
+            if directions.len() >= field.len() {
+                return None;
+            }
+            let direction = field.get(&current)?;
+            current = match direction {
+                | Direction::Up => ChartedCoordinate(current.0.checked_sub(1)?, current.1),
+                | Direction::Down => ChartedCoordinate(current.0 + 1, current.1),
+                | Direction::Left => ChartedCoordinate(current.0, current.1.checked_sub(1)?),
+                | Direction::Right => ChartedCoordinate(current.0, current.1 + 1),
+            };
+            directions.push(direction.clone());
+        }
+        Some(directions)
+    }
+
+    ///     Takes as parameter the starting coordinate and runs a single Dijkstra from it, returning a
+    ///     grid with the same shape of the robot_map passed to `init`: every tile reachable from `from`
+    ///     contains the cost of the shortest path to it, every other one (undiscovered, not walkable or
+    ///     unreachable) contains None. If `from` is not in the graph every cell is None.
+    ///     It is meant to be rendered as a heatmap of the travel cost.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             for row in cp.distance_field(my_coordinate) {
+    ///                 for cell in row {
+    ///                     match cell {
+    ///                         | None => print!("  . "),
+    ///                         | Some(cost) => print!("{:>3} ", cost),
+    ///                     }
+    ///                 }
+    ///                 println!();
+    ///             }
+    ///         }
+    pub fn distance_field(&self, from: ChartedCoordinate) -> Vec<Vec<Option<u32>>> {
+        let mut field: Vec<Vec<Option<u32>>> = self.indexes.iter().map(|row| vec![None; row.len()]).collect();
+        let start = match self.node_at(from) {
+            | None => return field,
+            | Some(node) => node,
+        };
+        let costs = dijkstra(&self.graph, start, None, |e| *e.weight());
+        for (node, cost) in costs.iter() {
+            let coordinate = self.graph[*node];
+            field[coordinate.0][coordinate.1] = Some(*cost);
+        }
+        field
+    }
+
+    ///     Same as shortest_path, but the search is run at the same time from both ends, stopping as
+    ///     soon as the two meet in the middle. On big maps with distant coordinates this explores far
+    ///     fewer tiles than a single search from `from`.
+    ///     The result is always the same returned by shortest_path, path included: when several paths
+    ///     share the minimum cost the choice among them follows the same rule.
+    pub fn shortest_path_bidirectional(
+        &self,
+        from: ChartedCoordinate,
+        to: ChartedCoordinate,
+    ) -> Option<(u32, Vec<ChartedCoordinate>)> {
+        let (start, goal) = (self.node_at(from)?, self.node_at(to)?);
+        if start == goal {
+            return Some((0, vec![from]));
+        }
+
+        // index 0 is the search from `from`, index 1 the one from `to`
+        let mut costs: [HashMap<NodeIndex, u32>; 2] = [HashMap::from([(start, 0)]), HashMap::from([(goal, 0)])];
+        let mut settled: [HashSet<NodeIndex>; 2] = [HashSet::new(), HashSet::new()];
+        let mut queues = [BinaryHeap::from([Reverse((0u32, start))]), BinaryHeap::from([Reverse((0u32, goal))])];
+
+        // best total cost found so far
+        let mut best: Option<u32> = None;
+        loop {
+            let (top_from, top_to) = match (queues[0].peek(), queues[1].peek()) {
+                | (Some(Reverse((a, _))), Some(Reverse((b, _)))) => (*a, *b),
+                | _ => break,
+            };
+            // once the tops add up to more than the best cost, every tile on a cheapest path has been
+            // settled by one of the two searches, which is what the choice of the path needs
+            if best.map_or(false, |best| top_from.saturating_add(top_to) > best) {
+                break;
+            }
+            let side = if top_from <= top_to { 0 } else { 1 };
+            let Reverse((cost, node)) = queues[side].pop().unwrap();
+            if !settled[side].insert(node) {
+                continue;
+            }
+            for edge in self.graph.edges(node) {
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                let next_cost = cost.saturating_add(*edge.weight());
+                if costs[side].get(&next).map_or(true, |current| next_cost < *current) {
+                    costs[side].insert(next, next_cost);
+                    queues[side].push(Reverse((next_cost, next)));
+                }
+                if let Some(other) = costs[1 - side].get(&next) {
+                    let total = costs[side][&next].saturating_add(*other);
+                    best = Some(best.map_or(total, |best| best.min(total)));
+                }
+            }
+        }
+        best?;
+
+        // distances to `to` within the settled tiles, starting from the exact ones of the search from `to`:
+        // they are exact for the tiles on a cheapest path, whose path to `to` never leaves the settled ones
+        let region: HashSet<NodeIndex> = settled[0].union(&settled[1]).copied().collect();
+        let mut to_goal: HashMap<NodeIndex, u32> = settled[1].iter().map(|node| (*node, costs[1][node])).collect();
+        let mut queue: BinaryHeap<Reverse<(u32, NodeIndex)>> =
+            to_goal.iter().map(|(node, cost)| Reverse((*cost, *node))).collect();
+        while let Some(Reverse((cost, node))) = queue.pop() {
+            if to_goal.get(&node).map_or(false, |best| cost > *best) {
+                continue;
+            }
+            for edge in self.graph.edges(node) {
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                let next_cost = cost.saturating_add(*edge.weight());
+                if region.contains(&next) && to_goal.get(&next).map_or(true, |current| next_cost < *current) {
+                    to_goal.insert(next, next_cost);
+                    queue.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+
+        let path = self.walk_cheapest(start, goal, &to_goal)?;
+        Some((*to_goal.get(&start)?, path))
+    }
+
+    ///     Returns the weight of the edge between the two coordinates, None if either of them is
+    ///     not in the graph or if they are not connected.
+    pub fn edge_weight(&self, a: ChartedCoordinate, b: ChartedCoordinate) -> Option<u32> {
+        let edge = self.graph.find_edge(self.node_at(a)?, self.node_at(b)?)?;
+        self.graph.edge_weight(edge).copied()
+    }
+
+    ///     Sets the weight of the edge between the two coordinates, for example to penalize a corridor
+    ///     known to be dangerous without rebuilding the whole graph.
+    ///     Returns Err if either coordinate is not in the graph or if they are not connected.
+    pub fn set_edge_weight(&mut self, a: ChartedCoordinate, b: ChartedCoordinate, weight: u32) -> Result<(), ()> {
+        let edge = match (self.node_at(a), self.node_at(b)) {
+            | (Some(a), Some(b)) => self.graph.find_edge(a, b).ok_or(())?,
+            | _ => return Err(()),
+        };
+        self.graph[edge] = weight;
+        // keep the A* heuristic admissible
+        if self.teleports_edges.contains_key(&edge) {
+            self.min_teleport_cost = self.min_teleport_cost.min(weight);
+        } else {
+            self.min_cost = self.min_cost.min(weight);
+        }
+        Ok(())
+    }
+
+    ///     Takes as parameter the starting coordinate and returns every tile that can be reached with at most
+    ///     `max_steps` moves, whatever their energy cost, `from` included: "what is in my movement range".
+    ///     Using a teleport counts as a single move. Tiles are returned in order of distance in moves.
+    ///     The result is empty if `from` is not in the graph.
+    pub fn reachable_within_steps(&self, from: ChartedCoordinate, max_steps: usize) -> Vec<ChartedCoordinate> {
+        self.reachable_within_steps_with(from, max_steps, true)
+    }
+
+    ///     Same as reachable_within_steps, but teleports are used only if `use_teleports` is true.
+    pub fn reachable_within_steps_with(
+        &self,
+        from: ChartedCoordinate,
+        max_steps: usize,
+        use_teleports: bool,
+    ) -> Vec<ChartedCoordinate> {
+        let start = match self.node_at(from) {
+            | None => return Vec::new(),
+            | Some(node) => node,
+        };
+        let mut visited = HashSet::from([start]);
+        let mut reached = vec![from];
+        let mut queue = VecDeque::from([(start, 0usize)]);
+        while let Some((node, steps)) = queue.pop_front() {
+            if steps == max_steps {
+                continue;
+            }
+            for edge in self.graph.edges(node) {
+                if !use_teleports && self.teleports_edges.contains_key(&edge.id()) {
+                    continue;
+                }
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                if visited.insert(next) {
+                    reached.push(self.graph[next]);
+                    queue.push_back((next, steps + 1));
+                }
+            }
+        }
+        reached
+    }
+
+    ///     Takes as parameter a coordinate and returns every neighbour of it in the graph with the cost of
+    ///     the edge to reach it, and true if the edge is a teleport (teleport neighbours can be far away).
+    ///     It is a cheap query, proportional to the number of neighbours, for greedy one-step decisions.
+    ///     Neighbours are sorted by row and then by column; the result is empty if `from` is not in the graph.
+    pub fn neighbor_costs(&self, from: ChartedCoordinate) -> Vec<(ChartedCoordinate, u32, bool)> {
+        let node = match self.node_at(from) {
+            | None => return Vec::new(),
+            | Some(node) => node,
+        };
+        let mut neighbors: Vec<(ChartedCoordinate, u32, bool)> = self
+            .graph
+            .edges(node)
+            .map(|edge| {
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                (self.graph[next], *edge.weight(), self.teleports_edges.contains_key(&edge.id()))
+            })
+            .collect();
+        neighbors.sort_by_key(|(c, _, _)| (c.0, c.1));
+        neighbors
+    }
+
+    ///     Returns the whole graph as a plain adjacency map: every coordinate in the graph is mapped to its
+    ///     neighbours with the cost of the edge to reach them, sorted as in `neighbor_costs`.
+    ///     Unlike the `graph` field it does not depend on petgraph, so it can be given to a custom solver.
+    ///     Teleport edges are included as they are, between the two teleports they connect.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let adjacency = cp.adjacency();
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             for (next, cost) in adjacency.get(&my_coordinate).cloned().unwrap_or_default() {
+    ///                 // feed your own search
+    ///             }
+    ///         }
+    pub fn adjacency(&self) -> HashMap<ChartedCoordinate, Vec<(ChartedCoordinate, u32)>> {
+        self.graph
+            .node_indices()
+            .map(|node| self.graph[node])
+            .map(|c| (c, self.neighbor_costs(c).into_iter().map(|(next, cost, _)| (next, cost)).collect()))
+            .collect()
+    }
+
+    ///     Renders the graph as an ASCII grid with the same size of the map passed to `init`, useful
+    ///     to understand why a path goes the way it does:
+    ///     - `*` is a tile of the provided path
+    ///     - `.` is a walkable tile present in the graph
+    ///     - ` ` (space) is an undiscovered or not walkable tile
+    ///
+    ///     every row of the map is a line of the returned string.
+    ///     ## Example:
+    ///
+    ///         if let Some((_, path)) = cp.shortest_path(my_coordinate, destination) {
+    ///             println!("{}", cp.render_path(&path));
+    ///         }
+    pub fn render_path(&self, path: &[ChartedCoordinate]) -> String {
+        let mut s = String::new();
+        for (i, row) in self.indexes.iter().enumerate() {
+            for (j, node) in row.iter().enumerate() {
+                if path.contains(&ChartedCoordinate(i, j)) {
+                    s.push('*');
+                } else if node.is_some() {
+                    s.push('.');
+                } else {
+                    s.push(' ');
+                }
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    ///     Takes as parameter two coordinates and an energy budget. It computes the optimal path from
+    ///     `from` to `toward` (as in shortest_path) and returns the longest prefix of it that can be
+    ///     walked spending at most `budget` energy, together with the last coordinate of the prefix.
+    ///     It allows to make progress toward a far away goal, tick after tick, without spending more
+    ///     energy than what is available.
+    ///     Returns None if there is no path, or if even the first step costs more than the budget.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let my_coordinate = ChartedCoordinate::from(self.get_coordinate());
+    ///             let energy = self.get_energy().get_energy_level() as u32;
+    ///             if let Some((stop, path)) = cp.farthest_affordable(my_coordinate, goal, energy / 2) {
+    ///                 // walk along path, ending in stop
+    ///             }
+    ///         }
+    pub fn farthest_affordable(
+        &self,
+        from: ChartedCoordinate,
+        toward: ChartedCoordinate,
+        budget: u32,
+    ) -> Option<(ChartedCoordinate, Vec<ChartedCoordinate>)> {
+        let (_, path) = self.shortest_path(from, toward)?;
+        let mut spent = 0u32;
+        let mut affordable = vec![path[0]];
+        for step in path.windows(2) {
+            let cost = self.step_weight(self.node_at(step[0])?, self.node_at(step[1])?)?;
+            spent = spent.saturating_add(cost);
+            if spent > budget {
+                break;
+            }
+            affordable.push(step[1]);
+        }
+        if affordable.len() == 1 && path.len() > 1 {
+            return None;
+        }
+        Some((*affordable.last()?, affordable))
+    }
+
+    ///     Takes as parameter a path, for example the one returned by shortest_path, and makes it
+    ///     easier to follow:
+    ///     - staircases of equal cost are straightened, swapping the order of the moves when the other
+    ///       corner of the square is walkable and the total cost does not change, so that the robot turns
+    ///       less often;
+    ///     - the intermediate coordinates of straight segments are removed.
+    ///
+    ///     The result is the list of the points where the robot has to turn (plus the two ends): every
+    ///     coordinate is reached from the previous one moving in a straight line, or through a teleport.
+    ///     The total cost is the same as the original path.
+    ///     ## Example:
+    ///
+    ///         // straight corridor
+    ///         let path = vec![ChartedCoordinate(0, 0), ChartedCoordinate(0, 1), ChartedCoordinate(0, 2)];
+    ///         assert_eq!(cp.smooth_path(&path), vec![ChartedCoordinate(0, 0), ChartedCoordinate(0, 2)]);
+    pub fn smooth_path(&self, path: &[ChartedCoordinate]) -> Vec<ChartedCoordinate> {
+        let mut path = path.to_vec();
+        let cost = |a: ChartedCoordinate, b: ChartedCoordinate| match (self.node_at(a), self.node_at(b)) {
+            | (Some(a), Some(b)) => self.step_weight(a, b),
+            | _ => None,
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for k in 1..path.len().saturating_sub(1) {
+                let (a, b, c) = (path[k - 1], path[k], path[k + 1]);
+                if !ChartedCoordinate::is_close_to(&a, &b)
+                    || !ChartedCoordinate::is_close_to(&b, &c)
+                    || a.0 == c.0
+                    || a.1 == c.1
+                {
+                    // not a corner
+                    continue;
+                }
+                let corner = ChartedCoordinate(a.0 + c.0 - b.0, a.1 + c.1 - b.1);
+                match (cost(a, b), cost(b, c), cost(a, corner), cost(corner, c)) {
+                    | (Some(w1), Some(w2), Some(w3), Some(w4)) if w1.saturating_add(w2) == w3.saturating_add(w4) => {
+                        let before = ChartedPaths::turns(&path);
+                        path[k] = corner;
+                        if ChartedPaths::turns(&path) < before {
+                            changed = true;
+                        } else {
+                            path[k] = b;
+                        }
+                    }
+                    | _ => {}
+                }
+            }
+        }
+
+        let mut smooth = Vec::new();
+        for (k, coordinate) in path.iter().enumerate() {
+            if k == 0
+                || k == path.len() - 1
+                || ChartedPaths::delta(path[k - 1], *coordinate) != ChartedPaths::delta(*coordinate, path[k + 1])
+            {
+                smooth.push(*coordinate);
+            }
+        }
+        smooth
+    }
+
+    ///     Serializes the whole graph (nodes, weighted edges, teleports and the coordinate conversion
+    ///     matrix) to a JSON string, that can later be loaded with `from_json` without calling `init`
+    ///     again, which would need a live World.
+    ///     The settings that affect how the graph is built again by `refresh` (the content cost, the base costs
+    ///     and the contents of `init_with_content_map`) are saved too. The predicate of `init_with_walkability`
+    ///     cannot be serialized, after loading `refresh` falls back to robotics_lib's `walk()`; the home set with
+    ///     `set_home` is not saved either, since it is cheap to set again.
+    ///
+    ///     Only available with the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        let serialized = SerializedPaths {
+            nodes: self.graph.node_weights().map(|c| (c.0, c.1)).collect(),
+            edges: self
+                .graph
+                .raw_edges()
+                .iter()
+                .map(|e| (e.source().index(), e.target().index(), e.weight))
+                .collect(),
+            teleports_edges: self.teleports_edges.keys().map(|e| e.index()).collect(),
+            indexes: self
+                .indexes
+                .iter()
+                .map(|row| row.iter().map(|node| node.map(|n| n.index())).collect())
+                .collect(),
+            teleports: self.teleports.iter().map(|c| (c.0, c.1)).collect(),
+            content_cost: self.content_cost,
+            use_heuristic: self.use_heuristic,
+            min_cost: self.min_cost,
+            elevations: self.elevations.clone(),
+            base_costs: self.base_costs.iter().map(|(tile_type, cost)| (tile_type.clone(), *cost)).collect(),
+            content_extras: self.content_extras.iter().map(|(c, cost)| ((c.0, c.1), *cost)).collect(),
+        };
+        // only plain data is serialized, so this cannot fail
+        serde_json::to_string(&serialized).unwrap_or_default()
+    }
+
+    ///     Rebuilds a ChartedPaths from a string produced by `to_json`.
+    ///
+    ///     The returned struct is a charting tool like any other, so it is obtained through
+    ///     `ChartingTools::tool` and counts toward the limit of tools alive at the same time.
+    ///     Returns Err with a description of the problem if the tool limit has been reached or
+    ///     if the string is not a valid export.
+    ///
+    ///     Only available with the `serde` feature.
+    ///     ## Example:
+    ///
+    ///         let json = cp.to_json();
+    ///         drop(cp);
+    ///         let cp = ChartedPaths::from_json(&json).unwrap();
+    #[cfg(feature = "serde")]
+    pub fn from_json(s: &str) -> Result<ChartedPaths, String> {
+        let serialized: SerializedPaths = serde_json::from_str(s).map_err(|e| e.to_string())?;
+        let nodes = serialized.nodes.len();
+        let edges = serialized.edges.len();
+        if serialized.edges.iter().any(|(a, b, _)| *a >= nodes || *b >= nodes)
+            || serialized.teleports_edges.iter().any(|e| *e >= edges)
+            || serialized.indexes.iter().flatten().flatten().any(|n| *n >= nodes)
+        {
+            return Err("the graph references nodes or edges that do not exist".to_string());
+        }
+
+        let mut paths = ChartingTools::tool::<ChartedPaths>()
+            .map_err(|n| format!("there are currently {n} tools active, you cannot get another"))?;
+        for (row, col) in serialized.nodes.into_iter() {
+            paths.graph.add_node(ChartedCoordinate(row, col));
+        }
+        for (a, b, weight) in serialized.edges.into_iter() {
+            paths.graph.add_edge(NodeIndex::new(a), NodeIndex::new(b), weight);
+        }
+        paths.teleports_edges = serialized.teleports_edges.into_iter().map(|e| (EdgeIndex::new(e), true)).collect();
+        paths.indexes = serialized
+            .indexes
+            .into_iter()
+            .map(|row| row.into_iter().map(|node| node.map(NodeIndex::new)).collect())
+            .collect();
+        paths.teleports = serialized.teleports.into_iter().map(ChartedCoordinate::from).collect();
+        paths.content_cost = serialized.content_cost;
+        paths.use_heuristic = serialized.use_heuristic;
+        paths.elevations = serialized.elevations;
+        paths.base_costs = serialized.base_costs.into_iter().collect();
+        paths.content_extras =
+            serialized.content_extras.into_iter().map(|(c, cost)| (ChartedCoordinate::from(c), cost)).collect();
+        paths.update_min_costs();
+        Ok(paths)
+    }
+
+    ///     Takes as parameter a path, for example the one returned by shortest_path, and returns it as a
+    ///     JSON array of `{"row": .., "col": ..}` objects, so that it can be given to external tools.
+    ///
+    ///     Only available with the `serde` feature.
+    ///     ## Example:
+    ///
+    ///         let path = vec![ChartedCoordinate(0, 0), ChartedCoordinate(0, 1)];
+    ///         assert_eq!(ChartedPaths::path_to_json(&path), r#"[{"row":0,"col":0},{"row":0,"col":1}]"#);
+    #[cfg(feature = "serde")]
+    pub fn path_to_json(path: &[ChartedCoordinate]) -> String {
+        let steps: Vec<SerializedStep> = path.iter().map(|c| SerializedStep { row: c.0, col: c.1 }).collect();
+        // only plain data is serialized, so this cannot fail
+        serde_json::to_string(&steps).unwrap_or_default()
+    }
+
+    ///     Reads back a path written by `path_to_json`.
+    ///     Returns Err with a description of the problem if the string is not a valid JSON path.
+    ///
+    ///     Only available with the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn path_from_json(s: &str) -> Result<Vec<ChartedCoordinate>, String> {
+        let steps: Vec<SerializedStep> = serde_json::from_str(s).map_err(|e| e.to_string())?;
+        Ok(steps.into_iter().map(|step| ChartedCoordinate(step.row, step.col)).collect())
+    }
+
+    fn delta(from: ChartedCoordinate, to: ChartedCoordinate) -> (i64, i64) {
+        (to.0 as i64 - from.0 as i64, to.1 as i64 - from.1 as i64)
+    }
+
+    fn turns(path: &[ChartedCoordinate]) -> usize {
+        path.windows(3)
+            .filter(|w| ChartedPaths::delta(w[0], w[1]) != ChartedPaths::delta(w[1], w[2]))
+            .count()
+    }
+
+    ///     Takes as parameter a path and returns how wide it is at its narrowest point: at every step, the number
+    ///     of walkable tiles in an unbroken line across the direction of travel, the tile of the path included.
+    ///     A choke point one tile wide gives 1, an open plain gives more. Between two routes with the same
+    ///     cost, the wider one is the safer. The tiles at both ends of a teleport are measured across the
+    ///     other step that touches them, if any; a path with fewer than two coordinates gives 0.
+    ///     ## Example:
+    ///
+    ///         fn process_tick(& mut self, world:&mut World){
+    ///             let a = cp.shortest_path(my_coordinate, goal).unwrap().1;
+    ///             let b = cp.shortest_path_no_teleport(my_coordinate, goal).unwrap().1;
+    ///             let safest = if cp.path_clearance(&a) >= cp.path_clearance(&b) { a } else { b };
+    ///         }
+    pub fn path_clearance(&self, path: &[ChartedCoordinate]) -> usize {
+        let mut clearance: Option<usize> = None;
+        for step in path.windows(2) {
+            if ChartedCoordinate::manhattan_distance(&step[0], &step[1]) != 1 {
+                continue;
+            }
+            // moving along a row the width is measured along the column, and the other way round
+            let vertical = step[0].0 == step[1].0;
+            for coordinate in step.iter() {
+                let width = self.run_length(*coordinate, vertical);
+                clearance = Some(clearance.map_or(width, |c| c.min(width)));
+            }
+        }
+        clearance.unwrap_or(0)
+    }
+
+    fn run_length(&self, coordinate: ChartedCoordinate, vertical: bool) -> usize {
+        if self.node_at(coordinate).is_none() {
+            return 0;
+        }
+        let step = |c: ChartedCoordinate, forward: bool| -> Option<ChartedCoordinate> {
+            match (vertical, forward) {
+                | (true, true) => Some(ChartedCoordinate(c.0 + 1, c.1)),
+                | (true, false) => c.0.checked_sub(1).map(|r| ChartedCoordinate(r, c.1)),
+                | (false, true) => Some(ChartedCoordinate(c.0, c.1 + 1)),
+                | (false, false) => c.1.checked_sub(1).map(|col| ChartedCoordinate(c.0, col)),
+            }
+        };
+        let mut length = 1;
+        for forward in [true, false] {
+            let mut current = coordinate;
+            while let Some(next) = step(current, forward).filter(|next| self.node_at(*next).is_some()) {
+                length += 1;
+                current = next;
+            }
+        }
+        length
+    }
+
+    ///     Takes as parameter a path and returns the cost of each of its steps, as stored in the
+    ///     graph: the i-th value is the cost of going from path[i] to path[i+1], so the result has one
+    ///     element less than the path (and is empty for paths of one or zero coordinates).
+    ///     It allows to budget the energy step by step and to spot expensive steps, like teleports.
+    ///     Steps between coordinates that are not connected in the graph cost u32::MAX.
+    pub fn path_step_costs(&self, path: &[ChartedCoordinate]) -> Vec<u32> {
+        path.windows(2)
+            .map(|step| match (self.node_at(step[0]), self.node_at(step[1])) {
+                | (Some(a), Some(b)) => self.step_weight(a, b).unwrap_or(u32::MAX),
+                | _ => u32::MAX,
+            })
+            .collect()
+    }
+
+    fn remove_edge(&mut self, edge: EdgeIndex) {
+        // same as petgraph's remove_edge, but it also mirrors the index swap in teleports_edges
+        let last = EdgeIndex::new(self.graph.edge_count() - 1);
+        self.graph.remove_edge(edge);
+        self.teleports_edges.remove(&edge);
+        if last != edge {
+            if let Some(value) = self.teleports_edges.remove(&last) {
+                self.teleports_edges.insert(edge, value);
+            }
+        }
+    }
+
+    fn elevation_of(&self, coordinate: ChartedCoordinate) -> usize {
+        self.elevations.get(coordinate.0).and_then(|row| row.get(coordinate.1).copied().flatten()).unwrap_or(0)
+    }
+
+    fn step_weight(&self, a: NodeIndex, b: NodeIndex) -> Option<u32> {
+        // two adjacent teleports are connected by two edges, the cheapest one is the one a path would use
+        self.graph.edges_connecting(a, b).map(|e| *e.weight()).min()
+    }
+
+    fn heuristic(&self, node: NodeIndex, to: ChartedCoordinate) -> u32 {
+        if !self.use_heuristic {
+            return 0;
+        }
+        let distance = ChartedCoordinate::manhattan_distance(&self.graph[node], &to) as u32;
+        let estimate = distance.saturating_mul(self.min_cost);
+        if self.teleports_edges.is_empty() {
+            estimate
+        } else {
+            // a path through a teleport may be cheaper than walking, but it costs at least the cheapest teleport
+            estimate.min(self.min_teleport_cost)
+        }
+    }
+
+    ///     The inverse of coordinates_to_direction: takes a starting coordinate and a list of moves and
+    ///     returns the coordinates visited following them, starting coordinate included (like the paths
+    ///     returned by shortest_path). `dim` is the side of the world; if any move would leave the map it
+    ///     returns Err. Useful to validate a planned sequence of moves before executing it.
+    ///     ## Example:
+    ///     ```
+    ///       use charting_tools::charted_paths::ChartedPaths;
+    ///       use charting_tools::charted_coordinate::ChartedCoordinate;
+    ///       use robotics_lib::interface::Direction;
+    ///
+    ///       let path = ChartedPaths::apply_directions(
+    ///           ChartedCoordinate(0, 0), &[Direction::Down, Direction::Right], 3).unwrap();
+    ///       assert_eq!(path, vec![ChartedCoordinate(0, 0), ChartedCoordinate(1, 0), ChartedCoordinate(1, 1)]);
+    ///       assert!(ChartedPaths::apply_directions(ChartedCoordinate(0, 0), &[Direction::Up], 3).is_err());
+    ///     ```
+    pub fn apply_directions(
+        start: ChartedCoordinate,
+        directions: &[Direction],
+        dim: usize,
+    ) -> Result<Vec<ChartedCoordinate>, ()> {
+        if !(start < dim) {
+            return Err(());
+        }
+        let mut path = vec![start];
+        let mut current = start;
+        for direction in directions.iter() {
+            current = match direction {
+                | Direction::Up if current.0 > 0 => ChartedCoordinate(current.0 - 1, current.1),
+                | Direction::Down if current.0 + 1 < dim => ChartedCoordinate(current.0 + 1, current.1),
+                | Direction::Left if current.1 > 0 => ChartedCoordinate(current.0, current.1 - 1),
+                | Direction::Right if current.1 + 1 < dim => ChartedCoordinate(current.0, current.1 + 1),
+                | _ => return Err(()),
+            };
+            path.push(current);
+        }
+        Ok(path)
+    }
+
+    fn check_boundaries(&self, from: ChartedCoordinate, to: ChartedCoordinate) -> bool {
+        let x = self.node_at(from);
+        let y = self.node_at(to);
+        return x.is_some() && y.is_some();
+    }
+
+    fn node_at(&self, coordinate: ChartedCoordinate) -> Option<NodeIndex> {
+        // bounds-safe lookup, an empty or not yet initialized graph has no rows at all
+        match self.indexes.get(coordinate.0) {
+            | None => None,
+            | Some(row) => row.get(coordinate.1).copied().flatten(),
+        }
+    }
+
+    fn dijkstra_tree(&self, sources: &[NodeIndex]) -> (HashMap<NodeIndex, u32>, HashMap<NodeIndex, NodeIndex>) {
+        // plain Dijkstra that, unlike petgraph's one, also keeps the predecessor of every node,
+        // so that paths can be rebuilt walking the predecessors back to a source
+        let mut costs: HashMap<NodeIndex, u32> = HashMap::new();
+        let mut predecessors: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        for source in sources.iter() {
+            costs.insert(*source, 0);
+            queue.push(Reverse((0u32, *source)));
+        }
+        while let Some(Reverse((cost, node))) = queue.pop() {
+            if costs.get(&node).map_or(false, |best| cost > *best) {
+                continue;
+            }
+            for edge in self.graph.edges(node) {
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                let next_cost = cost.saturating_add(*edge.weight());
+                if costs.get(&next).map_or(true, |best| next_cost < *best) {
+                    costs.insert(next, next_cost);
+                    predecessors.insert(next, node);
+                    queue.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        (costs, predecessors)
+    }
+
+    fn distances_to(&self, goal: NodeIndex, toward: NodeIndex) -> Option<HashMap<NodeIndex, u32>> {
+        // A* from goal to `toward` that, instead of stopping when it gets there, goes on until every tile that could
+        // lie on a cheapest path between the two has been settled: the distances to goal of those tiles are then
+        // exact, the ones of the other tiles reached are only upper bounds. None if `toward` cannot be reached
+        let target = self.graph[toward];
+        let mut costs: HashMap<NodeIndex, u32> = HashMap::from([(goal, 0)]);
+        let mut settled: HashSet<NodeIndex> = HashSet::new();
+        let mut queue = BinaryHeap::from([Reverse((self.heuristic(goal, target), 0u32, goal))]);
+        let mut best: Option<u32> = None;
+        while let Some(Reverse((estimate, cost, node))) = queue.pop() {
+            if best.map_or(false, |best| estimate > best) {
+                break;
+            }
+            if !settled.insert(node) {
+                continue;
+            }
+            if node == toward {
+                best = Some(cost);
+            }
+            for edge in self.graph.edges(node) {
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                let next_cost = cost.saturating_add(*edge.weight());
+                if costs.get(&next).map_or(true, |current| next_cost < *current) {
+                    costs.insert(next, next_cost);
+                    queue.push(Reverse((next_cost.saturating_add(self.heuristic(next, target)), next_cost, next)));
+                }
+            }
+        }
+        best.map(|_| costs)
+    }
+
+    fn walk_cheapest(
+        &self,
+        start: NodeIndex,
+        goal: NodeIndex,
+        to_goal: &HashMap<NodeIndex, u32>,
+    ) -> Option<Vec<ChartedCoordinate>> {
+        // the cheapest path chosen by shortest_path: the next tile is always the smallest one from which goal
+        // can be reached at the minimum cost, which only needs the exact distances of the tiles on a cheapest path
+        self.walk_smallest(start, goal, |node, next, weight| match (to_goal.get(&node), to_goal.get(&next)) {
+            | (Some(left), Some(next_left)) => next_left.saturating_add(weight) == *left,
+            | _ => false,
+        })
+    }
+
+    fn walk_smallest(
+        &self,
+        start: NodeIndex,
+        goal: NodeIndex,
+        allowed: impl Fn(NodeIndex, NodeIndex, u32) -> bool,
+    ) -> Option<Vec<ChartedCoordinate>> {
+        // depth-first walk from start to goal along the allowed edges, always trying the smallest next coordinate
+        // first. Edges costing 0 can make the allowed edges form a cycle, so a choice leading nowhere is undone
+        let candidates = |node: NodeIndex| -> Vec<NodeIndex> {
+            let mut next: Vec<NodeIndex> = self
+                .graph
+                .edges(node)
+                .filter_map(|e| {
+                    let next = if e.source() == node { e.target() } else { e.source() };
+                    if allowed(node, next, *e.weight()) { Some(next) } else { None }
+                })
+                .collect();
+            // the smallest one is the last, so that it is the first popped
+            next.sort_by_key(|n| Reverse((self.graph[*n].0, self.graph[*n].1)));
+            next
+        };
+
+        let mut visited = HashSet::from([start]);
+        let mut stack = vec![(start, candidates(start))];
+        while let Some((node, left)) = stack.last_mut() {
+            if *node == goal {
+                return Some(stack.iter().map(|(node, _)| self.graph[*node]).collect());
+            }
+            match left.pop() {
+                | None => {
+                    stack.pop();
+                }
+                | Some(next) => {
+                    if visited.insert(next) {
+                        stack.push((next, candidates(next)));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn index_to_coordinate(&self, node_index: &NodeIndex) -> Option<ChartedCoordinate> {
+        let dim = self.indexes.len();
+        for i in 0..dim {
             for (index, current_node) in self.indexes[i].iter().enumerate() {
                 match current_node {
                     | None => {}
@@ -378,19 +2623,19 @@ impl ChartedPaths {
     }
     fn adds_nodes(
         matrix: &Vec<Vec<Option<Tile>>>,
-        dim: usize,
         indexes: &mut Vec<Vec<Option<NodeIndex>>>,
         graph: &mut UnGraph<ChartedCoordinate, u32>,
         teleports: &mut Vec<ChartedCoordinate>,
+        walkable: &dyn Fn(&Tile) -> bool,
     ) {
-        // takes matrix as a reference of the robot map and the dimension of the map.
+        // takes matrix as a reference of the robot map, whose rows are scanned as long as they are.
         // creates a graph with the walkable seen nodes,
         // changes the matrix of Node-indexes of the pathfinder that will be used to retrieve graph Indexes
         // add the teleports NodeIndexes
-        for i in 0..dim {
-            let mut row: Vec<Option<NodeIndex>> = Vec::with_capacity(dim);
-            for j in 0..dim {
-                match matrix[i][j].as_ref() {
+        for (i, tiles) in matrix.iter().enumerate() {
+            let mut row: Vec<Option<NodeIndex>> = Vec::with_capacity(tiles.len());
+            for (j, tile) in tiles.iter().enumerate() {
+                match tile.as_ref() {
                     // check if the robot discovered that Tile
                     | None => {
                         row.push(None);
@@ -398,11 +2643,11 @@ impl ChartedPaths {
                     | Some(present_tile) => {
                         // this checks if the robot walked over the tile or if he has
                         // seen it.
-                        if !present_tile.tile_type.properties().walk() {
+                        if !walkable(present_tile) {
                             // Since the vector contains also Tiles that the robot has seen
                             // we have to check if the tile we are looking at is walkable or not
                             // if not i don't need it in the graph but i still need in indexes
-                            // to keep the matrix dim x dim
+                            // to keep the same shape of the map
                             row.push(None);
                             continue;
                         }
@@ -423,14 +2668,24 @@ impl ChartedPaths {
         to: &ChartedCoordinate,
         map: &Vec<Vec<Option<Tile>>>,
         world: &World,
+        content_cost: bool,
+        base_costs: &HashMap<TileType, u32>,
     ) -> u32 {
         if ChartedCoordinate::is_close_to(from, to) {
             let env_cond = look_at_sky(world);
 
             match (map[from.0][from.1].as_ref(), map[to.0][to.1].as_ref()) {
                 | (Some(tile_from), Some(tile_to)) => {
-                    let base_cost = tile_from.tile_type.properties().cost();
-                    let base_cost = calculate_cost_go_with_environment(base_cost, env_cond, tile_from.tile_type) as u32;
+                    let base_cost = match base_costs.get(&tile_from.tile_type) {
+                        | Some(cost) => *cost as usize,
+                        | None => tile_from.tile_type.properties().cost(),
+                    };
+                    let mut base_cost =
+                        calculate_cost_go_with_environment(base_cost, env_cond, tile_from.tile_type) as u32;
+                    if content_cost {
+                        base_cost += ChartedPaths::clearing_cost(&tile_to.content);
+                    }
+                    // going downhill or on flat ground has no elevation term, only climbing costs more
                     if tile_from.elevation < tile_to.elevation {
                         let elevation_cost = (tile_to.elevation as i32 - tile_from.elevation as i32).pow(2) as u32;
                         return (base_cost + elevation_cost);
@@ -445,4 +2700,449 @@ impl ChartedPaths {
             u32::MAX
         }
     }
+
+    fn undirected_weight(
+        a: &ChartedCoordinate,
+        b: &ChartedCoordinate,
+        map: &Vec<Vec<Option<Tile>>>,
+        world: &World,
+        content_cost: bool,
+        base_costs: &HashMap<TileType, u32>,
+    ) -> u32 {
+        // the edge is used in both directions, keep the costlier one (the uphill reading)
+        ChartedPaths::eval_weight(a, b, map, world, content_cost, base_costs).max(ChartedPaths::eval_weight(
+            b,
+            a,
+            map,
+            world,
+            content_cost,
+            base_costs,
+        ))
+    }
+
+    fn terrain_weight(
+        &self,
+        a: &ChartedCoordinate,
+        b: &ChartedCoordinate,
+        map: &Vec<Vec<Option<Tile>>>,
+        world: &World,
+    ) -> u32 {
+        // the weight of the edge between two adjacent tiles, with the contents of init_with_content_map
+        ChartedPaths::undirected_weight(a, b, map, world, self.content_cost, &self.base_costs)
+            .saturating_add(self.content_extra(a))
+            .saturating_add(self.content_extra(b))
+    }
+
+    fn content_extra(&self, coordinate: &ChartedCoordinate) -> u32 {
+        self.content_extras.get(coordinate).copied().unwrap_or(0)
+    }
+
+    fn is_walkable(&self, tile: &Tile) -> bool {
+        match self.walkable.as_ref() {
+            | Some(walkable) => walkable(tile),
+            | None => tile.tile_type.properties().walk(),
+        }
+    }
+
+    fn clearing_cost(content: &Content) -> u32 {
+        // contents that have to be destroyed before the robot can pass through the tile
+        match content {
+            | Content::Rock(_) | Content::Tree(_) | Content::Bush(_) | Content::Fire => {
+                content.properties().cost() as u32
+            }
+            | _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{grid, uncounted, with_world};
+
+    #[test]
+    fn init_on_empty_and_unwalkable_maps() {
+        with_world(|_, world| {
+            for map in [Vec::new(), grid(&["~"])] {
+                let mut cp = uncounted::<ChartedPaths>();
+                cp.init(&map, world);
+                let origin = ChartedCoordinate(0, 0);
+                assert_eq!(cp.graph.node_count(), 0);
+                assert!(cp.teleports().is_empty());
+                assert!(cp.shortest_path(origin, origin).is_none());
+                assert!(cp.shortest_path_cost(origin, origin).is_none());
+                assert!(cp.shortest_path_cost_a_star(origin, origin).is_none());
+                assert!(cp.shortest_path_to_nearest_walkable(origin, origin).is_none());
+                assert!(cp.nearest_reachable(origin, &[origin]).is_none());
+                assert!(cp.nearest_teleport(origin).is_none());
+            }
+        });
+    }
+
+    #[test]
+    fn heuristic_keeps_paths_optimal() {
+        with_world(|_, world| {
+            let map = grid(&["T....", ".~~~.", ".~T~.", ".~~~.", "....T"]);
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.init(&map, world);
+            // a teleport cheaper than walking the manhattan distance between its ends
+            cp.set_edge_weight(ChartedCoordinate(0, 0), ChartedCoordinate(4, 4), 2).unwrap();
+            let mut plain = cp.clone();
+            plain.set_heuristic(false);
+
+            let nodes: Vec<ChartedCoordinate> = cp.graph.node_indices().map(|node| cp.graph[node]).collect();
+            for a in nodes.iter() {
+                for b in nodes.iter() {
+                    let expected = plain.cost(*a, *b, PathAlgorithm::Dijkstra);
+                    assert!(expected.is_some());
+                    assert_eq!(cp.shortest_path_cost_a_star(*a, *b), expected);
+                    assert_eq!(plain.shortest_path_cost_a_star(*a, *b), expected);
+                    assert_eq!(cp.shortest_path(*a, *b).map(|(cost, _)| cost as u32), expected);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn tie_break_backtracks_out_of_free_dead_ends() {
+        with_world(|_, world| {
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.init(&grid(&["..", ".~", ".~"]), world);
+            // (0, 1) is the smallest next tile, but from there the only way on is back to (0, 0)
+            cp.set_edge_weight(ChartedCoordinate(0, 0), ChartedCoordinate(0, 1), 0).unwrap();
+            let (from, to) = (ChartedCoordinate(0, 0), ChartedCoordinate(2, 0));
+            let expected = vec![from, ChartedCoordinate(1, 0), to];
+            for tie_break in [TieBreak::SmallestCoordinates, TieBreak::FewestHops] {
+                let (cost, path) = cp.shortest_path_tie_break(from, to, tie_break).unwrap();
+                assert_eq!(Some(cost as u32), cp.shortest_path_cost(from, to));
+                assert_eq!(path, expected);
+            }
+        });
+    }
+
+    #[test]
+    fn bidirectional_matches_shortest_path() {
+        with_world(|_, world| {
+            // open grass is full of paths with the same cost, the teleports add shortcuts among them
+            let map = grid(&["T.....", "......", "..~~..", "..~T..", "......", ".....T"]);
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.init(&map, world);
+            assert_eq!(cp.teleports().len(), 3);
+
+            let nodes: Vec<ChartedCoordinate> = cp.graph.node_indices().map(|node| cp.graph[node]).collect();
+            for a in nodes.iter() {
+                for b in nodes.iter() {
+                    let expected = cp.shortest_path(*a, *b).map(|(cost, path)| (cost as u32, path));
+                    assert!(expected.is_some());
+                    assert_eq!(cp.shortest_path_bidirectional(*a, *b), expected);
+                    let tie_break = cp.shortest_path_tie_break(*a, *b, TieBreak::SmallestCoordinates);
+                    assert_eq!(tie_break.map(|(cost, path)| (cost as u32, path)), expected);
+                }
+            }
+        });
+    }
+
+    /// compares two graphs through the coordinates, since the indexes of refresh differ from the ones of init
+    fn assert_same_graph(a: &ChartedPaths, b: &ChartedPaths) {
+        let nodes = |cp: &ChartedPaths| cp.graph.node_indices().map(|node| cp.graph[node]).collect::<HashSet<_>>();
+        let edges = |cp: &ChartedPaths| {
+            cp.graph
+                .edge_references()
+                .map(|edge| {
+                    let (x, y) = (cp.graph[edge.source()], cp.graph[edge.target()]);
+                    let (x, y) = ((x.0, x.1), (y.0, y.1));
+                    ((x.min(y), x.max(y)), *edge.weight())
+                })
+                .collect::<HashMap<_, _>>()
+        };
+        assert_eq!(nodes(a), nodes(b));
+        assert_eq!(a.graph.edge_count(), b.graph.edge_count());
+        assert_eq!(edges(a), edges(b));
+        assert_eq!(a.teleports, b.teleports);
+        assert_eq!((a.min_cost, a.min_teleport_cost), (b.min_cost, b.min_teleport_cost));
+    }
+
+    #[test]
+    fn incremental_init_matches_init() {
+        with_world(|_, world| {
+            // more columns than rows, so every call stops somewhere in the middle of a row
+            let map = grid(&["T.~..", "..:..", "~~~..", "..T.."]);
+            let swim = |tile: &Tile| tile.tile_type.properties().walk() || tile.tile_type == TileType::DeepWater;
+            let (mut incremental, mut expected) = (uncounted::<ChartedPaths>(), uncounted::<ChartedPaths>());
+            expected.init(&map, world);
+            let mut calls = 1;
+            while !incremental.init_incremental(&map, world, 3) {
+                calls += 1;
+            }
+            assert!(calls > 1);
+            assert_same_graph(&incremental, &expected);
+
+            // the predicate of init_with_walkability is kept by the next construction
+            expected.init_with_walkability(&map, world, swim);
+            incremental.init_with_walkability(&grid(&["~"]), world, swim);
+            while !incremental.init_incremental(&map, world, 3) {}
+            assert!(incremental.has_node(ChartedCoordinate(2, 0)));
+            assert_same_graph(&incremental, &expected);
+        });
+    }
+
+    #[test]
+    fn base_costs_change_the_weights_until_removed() {
+        with_world(|_, world| {
+            let map = grid(&["..", ".."]);
+            let weight = |cp: &ChartedPaths| {
+                let a = cp.node_at(ChartedCoordinate(0, 0)).unwrap();
+                let b = cp.node_at(ChartedCoordinate(0, 1)).unwrap();
+                cp.graph[cp.graph.find_edge(a, b).unwrap()]
+            };
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.init(&map, world);
+            let original = weight(&cp);
+
+            cp.set_base_costs(Some(HashMap::from([(TileType::Grass, 50)])));
+            cp.init(&map, world);
+            let overridden = weight(&cp);
+            assert!(overridden > original);
+            // the table is still there for the next construction
+            cp.init_with_walkability(&map, world, |tile| tile.tile_type.properties().walk());
+            assert_eq!(weight(&cp), overridden);
+
+            cp.set_base_costs(None);
+            cp.init(&map, world);
+            assert_eq!(weight(&cp), original);
+        });
+    }
+
+    #[test]
+    fn refresh_matches_init() {
+        with_world(|_, world| {
+            let old_map = grid(&["T.~..", ".....", "..~~.", ".#...", "....T"]);
+            let new_map = grid(&["T.~..", "..T..", "..--.", "..#..", "~...T"]);
+            let swim = |tile: &Tile| tile.tile_type.properties().walk() || tile.tile_type == TileType::DeepWater;
+            let content_map = ChartedMap::from_entries(vec![
+                (Content::Rock(1), ChartedCoordinate(0, 1)),
+                (Content::Tree(1), ChartedCoordinate(1, 2)),
+            ]);
+
+            let (mut refreshed, mut expected) = (uncounted::<ChartedPaths>(), uncounted::<ChartedPaths>());
+            refreshed.init_with_walkability(&old_map, world, swim);
+            refreshed.refresh(&old_map, &new_map, world);
+            expected.init_with_walkability(&new_map, world, swim);
+            assert!(expected.has_node(ChartedCoordinate(4, 0)));
+            assert_same_graph(&refreshed, &expected);
+
+            refreshed.init_with_content_map(&old_map, world, Some(&content_map));
+            refreshed.refresh(&old_map, &new_map, world);
+            expected.init_with_content_map(&new_map, world, Some(&content_map));
+            assert!(!expected.has_node(ChartedCoordinate(4, 0)));
+            assert_same_graph(&refreshed, &expected);
+        });
+    }
+
+    #[test]
+    fn queries_after_removing_a_node() {
+        with_world(|_, world| {
+            // the teleport in the bottom-right corner is the last node, and its teleport edge the last edge:
+            // removing an earlier node moves both of them into the freed indexes
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.init(&grid(&["T..", "...", "..T"]), world);
+            let mut expected = uncounted::<ChartedPaths>();
+            expected.init(&grid(&["T .", "...", "..T"]), world);
+
+            assert_eq!(cp.remove_node(ChartedCoordinate(0, 1)), Ok(()));
+            assert_eq!(cp.remove_node(ChartedCoordinate(0, 1)), Err(()));
+            assert_eq!(cp.graph.node_count(), expected.graph.node_count());
+            assert_eq!(cp.node_at(ChartedCoordinate(2, 2)), Some(NodeIndex::new(1)));
+            let nodes: Vec<ChartedCoordinate> = cp.graph.node_indices().map(|node| cp.graph[node]).collect();
+            for a in nodes.iter() {
+                for b in nodes.iter() {
+                    assert_eq!(cp.shortest_path(*a, *b), expected.shortest_path(*a, *b));
+                }
+            }
+
+            // the teleport edge is still known as such after the swap
+            assert_eq!(cp.teleports_edges.len(), 1);
+            assert_eq!(cp.teleports(), vec![ChartedCoordinate(0, 0), ChartedCoordinate(2, 2)]);
+            cp.set_edge_weight(ChartedCoordinate(0, 0), ChartedCoordinate(2, 2), 1).unwrap();
+            let teleport = cp.shortest_path(ChartedCoordinate(2, 2), ChartedCoordinate(0, 0));
+            assert_eq!(teleport, Some((1, vec![ChartedCoordinate(2, 2), ChartedCoordinate(0, 0)])));
+            let walk = cp.shortest_path_no_teleport(ChartedCoordinate(2, 2), ChartedCoordinate(0, 0)).unwrap();
+            assert_eq!(walk.1.len(), 5);
+            assert!(walk.1.iter().all(|c| *c != ChartedCoordinate(0, 1)));
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn json_round_trip() {
+        with_world(|_, world| {
+            let map = grid(&["T..", ".~.", "..T"]);
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.set_content_cost(true);
+            cp.set_base_costs(Some(HashMap::from([(TileType::Grass, 2)])));
+            cp.set_heuristic(false);
+            let content_map = ChartedMap::from_entries(vec![(Content::Rock(1), ChartedCoordinate(0, 1))]);
+            cp.init_with_content_map(&map, world, Some(&content_map));
+            cp.set_home(ChartedCoordinate(0, 0)).unwrap();
+
+            let mut loaded = ChartedPaths::from_json(&cp.to_json()).unwrap();
+            assert_same_graph(&loaded, &cp);
+            assert_eq!(loaded.indexes, cp.indexes);
+            assert_eq!(loaded.teleports_edges, cp.teleports_edges);
+            assert_eq!(loaded.elevations, cp.elevations);
+            assert_eq!(loaded.base_costs, cp.base_costs);
+            assert_eq!(loaded.content_extras, cp.content_extras);
+            assert_eq!((loaded.content_cost, loaded.use_heuristic), (true, false));
+            // the home is not saved
+            assert_eq!(loaded.cost_home(ChartedCoordinate(2, 2)), None);
+
+            // a refresh of the loaded graph still charges the saved contents
+            let changed = grid(&["T..", "...", "..T"]);
+            loaded.refresh(&map, &changed, world);
+            cp.refresh(&map, &changed, world);
+            assert_same_graph(&loaded, &cp);
+        });
+    }
+
+    #[test]
+    fn tour_cost_is_the_cost_of_the_planned_order() {
+        with_world(|_, world| {
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.init(&grid(&["T....", ".~~~.", "..=..", ".~~~.", "...:T"]), world);
+            let start = ChartedCoordinate(2, 2);
+            // the repeated point is visited once
+            let points =
+                [ChartedCoordinate(4, 3), ChartedCoordinate(0, 4), ChartedCoordinate(2, 0), ChartedCoordinate(4, 3)];
+
+            let order = cp.plan_visit_order(start, &points);
+            assert_eq!(order.len(), 3);
+            let mut expected = 0;
+            for (from, to) in std::iter::once(start).chain(order.iter().copied()).zip(order.iter().copied()) {
+                expected += cp.shortest_path_cost(from, to).unwrap();
+            }
+            assert_eq!(cp.estimate_tour_cost(start, &points), Some(expected));
+            // a point in the water cannot be reached
+            assert_eq!(cp.estimate_tour_cost(start, &[ChartedCoordinate(0, 4), ChartedCoordinate(1, 1)]), None);
+        });
+    }
+
+    #[test]
+    fn smooth_path_collapses_a_straight_corridor() {
+        with_world(|_, world| {
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.init(&grid(&["~~~~~~", "......", "~~~~~~"]), world);
+            let from = ChartedCoordinate(1, 0);
+            let to = ChartedCoordinate(1, 5);
+            let (cost, path) = cp.shortest_path(from, to).unwrap();
+            assert_eq!(path.len(), 6);
+
+            let smooth = cp.smooth_path(&path);
+            assert_eq!(smooth, vec![from, to]);
+            assert_eq!(cp.path_step_costs(&path).iter().sum::<u32>() as usize, cost);
+            // a staircase on open ground becomes a single turn with the same cost
+            cp.init(&grid(&["...", "...", "..."]), world);
+            let stairs = [(0, 0), (0, 1), (1, 1), (1, 2), (2, 2)].map(ChartedCoordinate::from).to_vec();
+            let smooth = cp.smooth_path(&stairs);
+            assert_eq!(smooth.len(), 3);
+            assert_eq!((smooth[0], smooth[2]), (ChartedCoordinate(0, 0), ChartedCoordinate(2, 2)));
+        });
+    }
+
+    #[test]
+    fn walkability_predicate_routes_across_water() {
+        with_world(|_, world| {
+            // two islands split by deep water
+            let map = grid(&["..~..", "..~..", "..~.."]);
+            let (from, to) = (ChartedCoordinate(1, 0), ChartedCoordinate(1, 4));
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.init(&map, world);
+            assert!(!cp.has_node(ChartedCoordinate(1, 2)));
+            // both ends are on the graph, so no path means the water really splits them
+            assert!(cp.has_node(from) && cp.has_node(to));
+            assert_eq!(cp.shortest_path(from, to), None);
+
+            cp.init_with_walkability(&map, world, |tile| {
+                tile.tile_type.properties().walk() || tile.tile_type == TileType::DeepWater
+            });
+            let (_, path) = cp.shortest_path(from, to).unwrap();
+            assert!(path.contains(&ChartedCoordinate(1, 2)));
+            assert_eq!(path.len(), 5);
+
+            // and the other way around, walkable tiles can be left out
+            cp.init_with_walkability(&grid(&["...", "...", "..."]), world, |tile| tile.tile_type != TileType::Grass);
+            assert_eq!(cp.graph.node_count(), 0);
+        });
+    }
+
+    #[test]
+    fn region_routes_match_the_full_graph() {
+        with_world(|_, world| {
+            // the region is open grass, so the cheapest routes inside it never need to leave it
+            let map = grid(&["~~.~...", ".......", ".......", "~......", ".......", ".......", "..~...~"]);
+            let center = ChartedCoordinate(3, 3);
+            let mut full = uncounted::<ChartedPaths>();
+            full.init(&map, world);
+            let mut region = uncounted::<ChartedPaths>();
+            region.init_region(&map, world, center, 2);
+
+            assert!(region.graph.node_count() < full.graph.node_count());
+            assert!(full.has_node(ChartedCoordinate(0, 2)));
+            assert_eq!(region.shortest_path(center, ChartedCoordinate(0, 2)), None);
+            assert_eq!(region.shortest_path(ChartedCoordinate(6, 5), center), None);
+            for (from, to) in [((3, 3), (1, 2)), ((1, 1), (5, 5)), ((2, 3), (4, 5)), ((5, 1), (3, 2))] {
+                let (from, to) = (ChartedCoordinate::from(from), ChartedCoordinate::from(to));
+                assert_eq!(region.shortest_path(from, to), full.shortest_path(from, to));
+            }
+        });
+    }
+
+    #[test]
+    fn one_edge_for_every_pair_of_teleports() {
+        with_world(|_, world| {
+            // the last two teleports touch diagonally, which must not change anything
+            let maps = [
+                ["....", "....", "...."],
+                ["T...", "....", "...."],
+                ["T...", "....", "...T"],
+                ["T...", "..T.", "...T"],
+            ];
+            for (count, rows) in maps.iter().enumerate() {
+                let mut cp = uncounted::<ChartedPaths>();
+                cp.init(&grid(rows), world);
+                assert_eq!(cp.teleports().len(), count);
+                assert_eq!(cp.teleports_edges.len(), count * count.saturating_sub(1) / 2);
+
+                let mut pairs = HashSet::new();
+                for edge in cp.teleports_edges.keys() {
+                    let (a, b) = cp.graph.edge_endpoints(*edge).unwrap();
+                    let (a, b) = (cp.graph[a], cp.graph[b]);
+                    assert!(cp.teleports().contains(&a) && cp.teleports().contains(&b) && a != b);
+                    assert!(pairs.insert(((a.0, a.1).min((b.0, b.1)), (a.0, a.1).max((b.0, b.1)))));
+                    assert_eq!(cp.graph[*edge], 30);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn instructions_through_a_teleport() {
+        with_world(|_, world| {
+            // the two islands are only connected by the teleports, and the first one has a single way to its teleport
+            let mut cp = uncounted::<ChartedPaths>();
+            cp.init(&grid(&["T.~", "~.~", "~~~", "..T"]), world);
+            let (from, to) = (ChartedCoordinate(1, 1), ChartedCoordinate(3, 0));
+
+            let (cost, instructions) = cp.shortest_path_instructions(from, to).unwrap();
+            assert_eq!(Some(cost), cp.shortest_path_cost(from, to));
+            let teleport = PathInstruction::Teleport { from: ChartedCoordinate(0, 0), to: ChartedCoordinate(3, 2) };
+            let expected = vec![
+                PathInstruction::Step(Direction::Up),
+                PathInstruction::Step(Direction::Left),
+                teleport,
+                PathInstruction::Step(Direction::Left),
+                PathInstruction::Step(Direction::Left),
+            ];
+            assert_eq!(instructions, expected);
+        });
+    }
 }
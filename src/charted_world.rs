@@ -1,13 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
 
+use robotics_lib::event::events::Event;
 use robotics_lib::interface::{discover_tiles, robot_map, robot_view};
 use robotics_lib::runner::Runnable;
 use robotics_lib::utils::LibError;
-use robotics_lib::world::tile::Tile;
+use robotics_lib::world::tile::{Content, Tile, TileType};
 use robotics_lib::world::World;
+#[cfg(feature = "bincode")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
-use crate::{ChartingTool, NUMBER, reserved::New};
+use crate::{ChartingTool, ChartingTools, Slot, reserved::New};
 use crate::charted_coordinate::ChartedCoordinate;
+use crate::charted_map::{ChartedMap, MapKey, SavedQuantity};
 
 /// struct: ChartedWorld
 ///
@@ -16,16 +23,80 @@ use crate::charted_coordinate::ChartedCoordinate;
 #[derive(Debug, Clone)]
 pub struct ChartedWorld {
     map: Vec<Vec<Option<Tile>>>,
-    len: usize,
+    rows: usize,
+    cols: usize,
+    offset: ChartedCoordinate,
+    annotations: HashMap<ChartedCoordinate, String>,
+    stale: HashSet<ChartedCoordinate>,
+    history: Vec<Vec<Change>>,
+    undone: Vec<Vec<Change>>,
+    batch: Option<Vec<Change>>,
+    slot: Slot,
+}
+
+/// maximum number of set operations that can be undone
+const HISTORY_LIMIT: usize = 64;
+
+/// a single tile change: where it happened, the tile before and the tile after
+type Change = (ChartedCoordinate, Option<Tile>, Option<Tile>);
+
+/// struct: ChartedWorldSnapshot
+///
+/// opaque copy of the state of a ChartedWorld, obtained via `ChartedWorld::snapshot`
+/// and given back to `ChartedWorld::restore` to roll back any change made in the meantime
+#[derive(Debug, Clone)]
+pub struct ChartedWorldSnapshot {
+    map: Vec<Vec<Option<Tile>>>,
+    rows: usize,
+    cols: usize,
+    offset: ChartedCoordinate,
+}
+
+/// plain representation of a ChartedWorld used by the binary export
+#[cfg(feature = "bincode")]
+#[derive(Serialize, Deserialize)]
+struct SerializedWorld {
+    map: Vec<Vec<Option<Tile>>>,
+    offset: (usize, usize),
+}
+
+/// struct: WorldStats
+///
+/// summary of a ChartedWorld computed in a single pass by `ChartedWorld::stats`, handy to be logged at every tick.
+/// the histograms list every tile type and content (in default form) found, in the order they are first met
+/// scanning the map row by row; elevations and bounds are `None` if nothing has been discovered yet
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct WorldStats {
+    pub discovered: usize,
+    pub explored_percentage: f64,
+    pub tile_types: Vec<(TileType, usize)>,
+    pub contents: Vec<(Content, usize)>,
+    pub min_elevation: Option<usize>,
+    pub max_elevation: Option<usize>,
+    pub bounds: Option<(ChartedCoordinate, ChartedCoordinate)>,
+}
+
+/// FNV-1a, used by `state_hash` since, unlike the std `DefaultHasher`, it is not seeded
+/// at random, so the same bytes give the same value in every run
+struct StableHasher(u64);
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
 }
 
 impl Drop for ChartedWorld {
     fn drop(&mut self) {
-        if let Ok(mut n) = NUMBER.lock() {
-            if *n > 0 {
-                *n = *n - 1;
-            }
-        }
+        self.slot.release();
     }
 }
 
@@ -33,11 +104,64 @@ impl ChartingTool for ChartedWorld {}
 
 impl New for ChartedWorld {
     fn new() -> Self {
-        Self { map: Vec::default(), len: 0 }
+        Self {
+            map: Vec::default(),
+            rows: 0,
+            cols: 0,
+            offset: ChartedCoordinate::default(),
+            annotations: HashMap::new(),
+            stale: HashSet::new(),
+            history: Vec::new(),
+            undone: Vec::new(),
+            batch: None,
+            slot: Slot::acquired(),
+        }
     }
 }
 
 impl ChartedWorld {
+    /// creates a ChartedWorld around a map that does not come from `robot_map()`, for example one loaded from disk
+    /// or produced by a generator.
+    ///
+    /// the returned struct is a charting tool like any other, so it is obtained through `ChartingTools::tool`
+    /// and counts toward the limit of tools alive at the same time. It fails if the limit has been reached
+    /// or if the map is not rectangular (every row must be as long as the first one)
+    pub fn from_map(map: Vec<Vec<Option<Tile>>>) -> Result<ChartedWorld, &'static str> {
+        let (_, cols) = shape(&map);
+        if map.iter().any(|row| row.len() != cols) {
+            return Err("the map is not rectangular");
+        }
+        let mut world = ChartingTools::tool::<ChartedWorld>().map_err(|_| "too many tools are currently active")?;
+        (world.rows, world.cols) = shape(&map);
+        world.map = map;
+        Ok(world)
+    }
+
+    /// serializes the map in a compact binary form, much smaller and faster to produce than JSON on big maps,
+    /// for example to checkpoint it between ticks or runs. Only the tiles and the `compact` offset are saved.
+    ///
+    /// only available with the `bincode` feature
+    #[cfg(feature = "bincode")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let serialized = SerializedWorld { map: self.map.clone(), offset: (self.offset.0, self.offset.1) };
+        // only plain data is serialized, so this cannot fail
+        bincode::serialize(&serialized).unwrap_or_default()
+    }
+
+    /// rebuilds a ChartedWorld from the bytes produced by `to_bytes`.
+    ///
+    /// like `from_map`, the returned struct counts toward the limit of tools alive at the same time.
+    /// It returns Err with a description of the problem if the limit has been reached or the bytes are not a valid export
+    ///
+    /// only available with the `bincode` feature
+    #[cfg(feature = "bincode")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<ChartedWorld, String> {
+        let serialized: SerializedWorld = bincode::deserialize(bytes).map_err(|e| e.to_string())?;
+        let mut world = ChartedWorld::from_map(serialized.map).map_err(|e| e.to_string())?;
+        world.offset = ChartedCoordinate::from(serialized.offset);
+        Ok(world)
+    }
+
     /// clears the map completely, setting all tiles to None
     pub fn clear(&mut self) {
         for row in self.map.iter_mut() {
@@ -48,19 +172,49 @@ impl ChartedWorld {
     }
 
     /// initializes the map to the one currently obtainable from the world via `robot_map()`
-    pub fn init(&mut self, world: &World) -> Result<(), &str> {
-        match robot_map(world) {
-            | None => Err("This literally should not be able to happen..."),
+    ///
+    /// like the update functions, it returns `LibError::OutOfBounds` if the map cannot be obtained,
+    /// in which case the current map is left untouched
+    pub fn init(&mut self, world: &World) -> Result<(), LibError> {
+        self.init_from(robot_map(world))
+    }
+
+    fn init_from(&mut self, map: Option<Vec<Vec<Option<Tile>>>>) -> Result<(), LibError> {
+        match map {
+            | None => Err(LibError::OutOfBounds),
             | Some(map) => {
-                self.len = map.len();
+                (self.rows, self.cols) = shape(&map);
                 self.map = map;
+                self.offset = ChartedCoordinate::default();
                 Ok(())
             }
         }
     }
 
     fn check_bounds(&self, coordinate: ChartedCoordinate) -> bool {
-        coordinate < self.len
+        // the shape of the map is checked as well, so that a coordinate within bounds can always be indexed
+        coordinate.0 < self.rows && coordinate.1 < self.cols && cell(&self.map, coordinate).is_some()
+    }
+
+    /// returns the top-left and bottom-right corners of the smallest box containing every
+    /// discovered tile, or `None` if nothing has been discovered yet
+    pub fn discovered_bounds(&self) -> Option<(ChartedCoordinate, ChartedCoordinate)> {
+        let mut bounds: Option<(ChartedCoordinate, ChartedCoordinate)> = None;
+        for (i, row) in self.map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                if tile.is_none() {
+                    continue;
+                }
+                bounds = match bounds {
+                    | None => Some((ChartedCoordinate(i, j), ChartedCoordinate(i, j))),
+                    | Some((top_left, bottom_right)) => Some((
+                        ChartedCoordinate(top_left.0.min(i), top_left.1.min(j)),
+                        ChartedCoordinate(bottom_right.0.max(i), bottom_right.1.max(j)),
+                    )),
+                };
+            }
+        }
+        bounds
     }
 
     /// returns the tile at the specified coordinate. It returns
@@ -72,11 +226,126 @@ impl ChartedWorld {
         Ok(self.map[coordinate.0][coordinate.1].clone())
     }
 
+    /// same as `at`, but it returns a reference to the tile instead of a copy,
+    /// which is cheaper when only a few of its fields have to be read
+    pub fn get_ref(&self, coordinate: ChartedCoordinate) -> Result<Option<&Tile>, LibError> {
+        if !self.check_bounds(coordinate) { return Err(LibError::OutOfBounds); }
+        Ok(self.map[coordinate.0][coordinate.1].as_ref())
+    }
+
+    /// same as `at`, but it returns only the type of the tile, without copying the rest of it
+    pub fn tile_type_at(&self, coordinate: ChartedCoordinate) -> Result<Option<TileType>, LibError> {
+        if !self.check_bounds(coordinate) { return Err(LibError::OutOfBounds); }
+        Ok(self.map[coordinate.0][coordinate.1].as_ref().map(|tile| tile.tile_type))
+    }
+
+    /// same as `at`, but it returns only the content of the tile, without copying the rest of it
+    pub fn content_at(&self, coordinate: ChartedCoordinate) -> Result<Option<Content>, LibError> {
+        if !self.check_bounds(coordinate) { return Err(LibError::OutOfBounds); }
+        Ok(self.map[coordinate.0][coordinate.1].as_ref().map(|tile| tile.content.clone()))
+    }
+
+    /// returns a mutable reference to the tile at the specified coordinate, to edit it in place. It returns
+    /// - `LibError::OutOfBounds` if the coordinate exceeds the world dimensions
+    /// - `None` if the desired tile has not yet been discovered or set
+    /// - the tile at the provided coordinate otherwise
+    pub fn at_mut(&mut self, coordinate: ChartedCoordinate) -> Result<Option<&mut Tile>, LibError> {
+        if !self.check_bounds(coordinate) { return Err(LibError::OutOfBounds); }
+        Ok(self.map[coordinate.0][coordinate.1].as_mut())
+    }
+
+    /// returns the whole row at the specified index, or `LibError::OutOfBounds` if the index exceeds the world dimensions
+    pub fn row(&self, r: usize) -> Result<&[Option<Tile>], LibError> {
+        if r >= self.rows || r >= self.map.len() { return Err(LibError::OutOfBounds); }
+        Ok(&self.map[r])
+    }
+
+    /// returns the whole column at the specified index, or `LibError::OutOfBounds` if the index exceeds the world dimensions.
+    ///
+    /// unlike `row` it returns an owned copy, since the tiles of a column are not contiguous in memory
+    pub fn column(&self, c: usize) -> Result<Vec<Option<Tile>>, LibError> {
+        if c >= self.cols { return Err(LibError::OutOfBounds); }
+        Ok(self.map.iter().map(|row| row.get(c).cloned().flatten()).collect())
+    }
+
+    /// consumes the ChartedWorld and returns the map it contains, without copying it like `get_map().clone()` would.
+    ///
+    /// the ChartedWorld is dropped, so its slot among the active tools is freed as usual
+    pub fn into_map(mut self) -> Vec<Vec<Option<Tile>>> {
+        std::mem::take(&mut self.map)
+    }
+
+    /// returns how many tiles of the map have been discovered or set (ie are Some)
+    pub fn count_discovered(&self) -> usize {
+        self.map.iter().flatten().filter(|tile| tile.is_some()).count()
+    }
+
+    /// returns which tiles have been discovered as a bitset: bit `row * cols + col` (counting from the least
+    /// significant bit of the first word) is set if the tile at (row, col) is discovered.
+    ///
+    /// it is a compact way to share what is known with another robot, the masks of two maps of the same size
+    /// can be combined with plain bitwise operations
+    pub fn explored_mask(&self) -> Vec<u64> {
+        let mut mask = vec![0u64; (self.rows * self.cols + 63) / 64];
+        for (i, row) in self.map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                if tile.is_some() && i < self.rows && j < self.cols {
+                    let bit = i * self.cols + j;
+                    mask[bit / 64] |= 1 << (bit % 64);
+                }
+            }
+        }
+        mask
+    }
+
+    /// returns how many tiles are discovered both in this map and in the provided mask,
+    /// obtained from `explored_mask` on a map of the same size. Extra words of the mask are ignored
+    pub fn coverage_overlap(&self, other_mask: &[u64]) -> usize {
+        self.explored_mask().iter().zip(other_mask.iter()).map(|(a, b)| (a & b).count_ones() as usize).sum()
+    }
+
+    /// returns how many discovered tiles are walkable, according to `tile_type.properties().walk()`
+    pub fn walkable_count(&self) -> usize {
+        self.map.iter().flatten().flatten().filter(|tile| tile.tile_type.properties().walk()).count()
+    }
+
+    /// returns how many discovered tiles are not walkable; undiscovered tiles are not counted
+    pub fn blocked_count(&self) -> usize {
+        self.count_discovered() - self.walkable_count()
+    }
+
+    /// returns the fraction (between 0 and 1) of the discovered tiles that are walkable,
+    /// 0 if nothing has been discovered yet
+    pub fn walkable_ratio(&self) -> f64 {
+        let discovered = self.count_discovered();
+        if discovered == 0 {
+            return 0.0;
+        }
+        self.walkable_count() as f64 / discovered as f64
+    }
+
     /// returns the whole map currently saved in the data structure
     pub fn get_map(&self) -> &Vec<Vec<Option<Tile>>> {
         &self.map
     }
 
+    /// returns an iterator over every cell of the map with its coordinate, in row-major order starting from (0, 0)
+    pub fn iter(&self) -> impl Iterator<Item = (ChartedCoordinate, &Option<Tile>)> + '_ {
+        self.map
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, tile)| (ChartedCoordinate(i, j), tile)))
+    }
+
+    /// same as `iter`, but in reverse: starting from the last cell of the last row and going back to (0, 0)
+    pub fn iter_rev(&self) -> impl Iterator<Item = (ChartedCoordinate, &Option<Tile>)> + '_ {
+        self.map
+            .iter()
+            .enumerate()
+            .rev()
+            .flat_map(|(i, row)| row.iter().enumerate().rev().map(move |(j, tile)| (ChartedCoordinate(i, j), tile)))
+    }
+
     /// sets the tile at the specified coordinate to the specified Tile.
     ///
     /// it will fail if the tile at said position has already been set or discovered (ie it is Some),
@@ -86,6 +355,7 @@ impl ChartedWorld {
         match self.at(coordinate) {
             Ok(None) => {
                 self.map[coordinate.0][coordinate.1] = Some(tile.clone());
+                self.record((coordinate, None, Some(tile.clone())));
                 Ok(())
             },
             Ok(Some(old_tile)) => Err((LibError::OperationNotAllowed, Some(old_tile.clone()))),
@@ -99,7 +369,8 @@ impl ChartedWorld {
     /// but it will if the coordinates are invalid
     pub fn set_overwrite(&mut self, tile: &Tile, coordinate: ChartedCoordinate) -> Result<(), LibError> {
         if !self.check_bounds(coordinate) { return Err(LibError::OutOfBounds); }
-        self.map[coordinate.0][coordinate.1] = Some(tile.clone());
+        let before = self.map[coordinate.0][coordinate.1].replace(tile.clone());
+        self.record((coordinate, before, Some(tile.clone())));
         Ok(())
     }
 
@@ -111,15 +382,19 @@ impl ChartedWorld {
         &mut self,
         to_change: &Vec<(&Tile, ChartedCoordinate)>,
     ) -> Result<(), (LibError, Option<Tile>, ChartedCoordinate)> {
+        self.batch = Some(Vec::new());
+        let mut result = Ok(());
         for i in to_change {
             match self.set(i.0, i.1) {
                 | Ok(()) => {}
                 | Err((err, option)) => {
-                    return Err((err, option, i.1));
+                    result = Err((err, option, i.1));
+                    break;
                 }
             }
         }
-        Ok(())
+        self.end_batch();
+        result
     }
 
     /// these names are getting out of hand...
@@ -127,46 +402,226 @@ impl ChartedWorld {
     ///
     /// it will only fail at the first invalid coordinate
     pub fn set_multiple_overwrite(&mut self, to_change: &Vec<(Tile, ChartedCoordinate)>) -> Result<(), (LibError, ChartedCoordinate)> {
+        self.batch = Some(Vec::new());
+        let mut result = Ok(());
         for (tile, coordinate) in to_change.iter() {
             match self.set_overwrite(tile, *coordinate) {
                 Ok(_) => {}
-                Err(err) => { return Err((err, *coordinate)); }
+                Err(err) => {
+                    result = Err((err, *coordinate));
+                    break;
+                }
             }
         }
-        Ok(())
+        self.end_batch();
+        result
+    }
+
+    /// writes every tile saved in the ChartedMap at its coordinate, like `set` (or `set_overwrite` if `overwrite` is true),
+    /// returning how many tiles were written. Tiles are written in order of row and then column, and the whole
+    /// operation is undone by a single `undo`.
+    ///
+    /// the elevation of every tile is the one saved as its `SavedQuantity::TileElevation` (0 if missing), while the content
+    /// keeps the default quantity of its key, since ChartedMap does not keep it for tiles.
+    /// It fails at the first coordinate that cannot be written, leaving the tiles written up to there
+    pub fn apply_map(&mut self, map: &ChartedMap<Tile>, overwrite: bool) -> Result<usize, (LibError, ChartedCoordinate)> {
+        let mut to_write: Vec<(Tile, ChartedCoordinate)> = Vec::new();
+        for (key, entries) in map.iter() {
+            for (coordinate, quantity) in entries.iter() {
+                let mut tile = key.clone();
+                tile.elevation = match quantity {
+                    | SavedQuantity::TileElevation(elevation) => *elevation,
+                    | _ => 0,
+                };
+                to_write.push((tile, *coordinate));
+            }
+        }
+        to_write.sort_by_key(|(_, c)| (c.0, c.1));
+
+        self.batch = Some(Vec::new());
+        let mut written = 0;
+        let mut result = Ok(());
+        for (tile, coordinate) in to_write.iter() {
+            let outcome = if overwrite {
+                self.set_overwrite(tile, *coordinate)
+            } else {
+                self.set(tile, *coordinate).map_err(|(err, _)| err)
+            };
+            match outcome {
+                | Ok(()) => written += 1,
+                | Err(err) => {
+                    result = Err((err, *coordinate));
+                    break;
+                }
+            }
+        }
+        self.end_batch();
+        result.map(|_| written)
+    }
+
+    /// adds to this map the tiles known by the other one, for example the map charted by another robot:
+    /// tiles that are None here are copied from the other map, while tiles known by both are kept as they are here.
+    ///
+    /// it is the same as `merge_with` keeping always the tile of this map
+    pub fn merge(&mut self, other: &ChartedWorld) {
+        self.merge_with(other, |mine, _| mine.clone())
+    }
+
+    /// same as `merge`, but when both maps know a tile and the two differ, the tile to keep is decided by `resolve`,
+    /// which receives this map's tile first and the other one's second, and can also return a blend of the two.
+    ///
+    /// only the area shared by the two maps is merged, if they have different sizes.
+    /// The whole merge is undone by a single `undo`
+    ///
+    /// ## Example
+    ///
+    ///     // keep the highest elevation seen by any robot
+    ///     world.merge_with(&other, |mine, theirs| {
+    ///         if theirs.elevation > mine.elevation { theirs.clone() } else { mine.clone() }
+    ///     });
+    pub fn merge_with<F: Fn(&Tile, &Tile) -> Tile>(&mut self, other: &ChartedWorld, resolve: F) {
+        self.batch = Some(Vec::new());
+        let len = self.map.len().min(other.map.len());
+        for i in 0..len {
+            for j in 0..self.map[i].len().min(other.map[i].len()) {
+                let merged = match (&self.map[i][j], &other.map[i][j]) {
+                    | (_, None) => continue,
+                    | (None, Some(theirs)) => theirs.clone(),
+                    | (Some(mine), Some(theirs)) if mine == theirs => continue,
+                    | (Some(mine), Some(theirs)) => resolve(mine, theirs),
+                };
+                if self.map[i][j].as_ref() != Some(&merged) {
+                    let before = self.map[i][j].replace(merged.clone());
+                    self.record((ChartedCoordinate(i, j), before, Some(merged)));
+                }
+            }
+        }
+        self.end_batch();
+    }
+
+    /// reverts the last set operation (`set`, `set_overwrite`, or a whole `set_multiple`/`set_multiple_overwrite`/merge),
+    /// putting back the tiles that were there before. Returns false if there is nothing to undo.
+    ///
+    /// only the last few set operations are remembered, and other kinds of changes (like the updates from the world)
+    /// are not recorded at all, so undoing restores the tiles as they were at the moment of the set
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            | None => false,
+            | Some(group) => {
+                for (coordinate, before, _) in group.iter().rev() {
+                    if self.check_bounds(*coordinate) {
+                        self.map[coordinate.0][coordinate.1] = before.clone();
+                    }
+                }
+                self.undone.push(group);
+                true
+            }
+        }
+    }
+
+    /// applies again the last set operation reverted by `undo`. Returns false if there is nothing to redo.
+    ///
+    /// any new set operation clears the operations that can be redone
+    pub fn redo(&mut self) -> bool {
+        match self.undone.pop() {
+            | None => false,
+            | Some(group) => {
+                for (coordinate, _, after) in group.iter() {
+                    if self.check_bounds(*coordinate) {
+                        self.map[coordinate.0][coordinate.1] = after.clone();
+                    }
+                }
+                self.history.push(group);
+                true
+            }
+        }
+    }
+
+    fn record(&mut self, change: Change) {
+        match self.batch.as_mut() {
+            | Some(batch) => batch.push(change),
+            | None => self.push_history(vec![change]),
+        }
+    }
+
+    fn end_batch(&mut self) {
+        if let Some(batch) = self.batch.take() {
+            if !batch.is_empty() {
+                self.push_history(batch);
+            }
+        }
+    }
+
+    fn push_history(&mut self, group: Vec<Change>) {
+        self.undone.clear();
+        self.history.push(group);
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.remove(0);
+        }
     }
 
     /// updates the tile at the specified coordinate to the one currently discovered in the world, be it Some(Tile) or None
+    ///
+    /// it returns `LibError::OutOfBounds` if the map cannot be obtained via `robot_map` (with the default coordinate)
+    /// or at the first invalid coordinate. In both cases no tile is updated, so a failed fetch never wipes the saved map
     pub fn update(&mut self, world: &World, coordinates: &Vec<ChartedCoordinate>) -> Result<(), (LibError, ChartedCoordinate)> {
-        let option = robot_map(world);
-        if option.is_none() {
-            return Err((LibError::OutOfBounds, ChartedCoordinate::default()));
-        }
+        self.update_reporting(world, coordinates).map(|_| ())
+    }
 
-        let map = option.unwrap();
+    /// same as `update`, but it returns every tile it actually changed as (coordinate, old tile, new tile),
+    /// in the order of the provided coordinates; tiles that were already up to date are not reported
+    pub fn update_reporting(
+        &mut self,
+        world: &World,
+        coordinates: &Vec<ChartedCoordinate>,
+    ) -> Result<Vec<(ChartedCoordinate, Option<Tile>, Option<Tile>)>, (LibError, ChartedCoordinate)> {
+        self.update_from(robot_map(world), coordinates)
+    }
+
+    fn update_from(
+        &mut self,
+        map: Option<Vec<Vec<Option<Tile>>>>,
+        coordinates: &Vec<ChartedCoordinate>,
+    ) -> Result<Vec<(ChartedCoordinate, Option<Tile>, Option<Tile>)>, (LibError, ChartedCoordinate)> {
+        let map = match map {
+            | None => return Err((LibError::OutOfBounds, ChartedCoordinate::default())),
+            | Some(map) => map,
+        };
+
+        // the coordinates are the world ones, which differ from those of the map after a `compact`
+        if let Some(point) = coordinates.iter().find(|p| self.to_local(**p).is_none() || cell(&map, **p).is_none()) {
+            return Err((LibError::OutOfBounds, *point));
+        }
+        let mut changes: Vec<Change> = Vec::new();
         for point in coordinates.iter() {
-            if !self.check_bounds(*point) {
-                return Err((LibError::OutOfBounds, *point));
-            }
-            if map[point.0][point.1].is_some() && self.map[point.0][point.1] != map[point.0][point.1] {
-                self.map[point.0][point.1] = map[point.0][point.1].clone();
-            } else if self.map[point.0][point.1].is_some() && map[point.0][point.1].is_none() {
-                self.map[point.0][point.1] = None;
+            let local = self.to_local(*point).unwrap();
+            self.stale.remove(&local);
+            let new = map[point.0][point.1].clone();
+            if self.map[local.0][local.1] != new {
+                let old = std::mem::replace(&mut self.map[local.0][local.1], new.clone());
+                changes.push((*point, old, new));
             }
         }
-        Ok(())
+        Ok(changes)
     }
 
     /// it will update all tiles that are currently visible to the robot according to the `robot_map` interface
+    ///
+    /// visible tiles that fall outside of the charted map (for example because it has not been initialized) are ignored
     pub fn update_viewed(&mut self, robot: &impl Runnable, world: &World) {
         let view = robot_view(robot, world);
         let robot_coordinate = ChartedCoordinate::from(robot.get_coordinate());
-        let conversion_coordinate = robot_coordinate - (1, 1);
 
-        for i in 0..view.len() {
-            for j in 0..view.len() {
-                if view[i][j].is_some() {
-                    self.map[i + conversion_coordinate.0][j + conversion_coordinate.1] = view[i][j].clone();
+        for (i, row) in view.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                // the view is centered on the robot, so (1, 1) is the robot itself
+                if tile.is_none() || robot_coordinate.0 + i < 1 || robot_coordinate.1 + j < 1 {
+                    continue;
+                }
+                let coordinate = ChartedCoordinate(robot_coordinate.0 + i - 1, robot_coordinate.1 + j - 1);
+                if let Some(local) = self.to_local(coordinate) {
+                    self.map[local.0][local.1] = tile.clone();
+                    self.stale.remove(&local);
                 }
             }
         }
@@ -188,7 +643,10 @@ impl ChartedWorld {
         ) {
             | Ok(hm) => {
                 for ((x, y), tile) in hm.iter() {
-                    self.map[*x][*y] = tile.clone();
+                    if let Some(local) = self.to_local(ChartedCoordinate(*x, *y)) {
+                        self.map[local.0][local.1] = tile.clone();
+                        self.stale.remove(&local);
+                    }
                 }
                 Ok(hm
                     .iter()
@@ -201,14 +659,50 @@ impl ChartedWorld {
         };
     }
 
+    /// keeps the map in sync with the robot's knowledge by reacting to the events emitted by robotics_lib,
+    /// so it can be called directly from the event handler of the robot instead of fetching `robot_map` every tick.
+    ///
+    /// it reacts to
+    /// - `Event::Moved(tile, coordinate)`: the tile the robot moved onto is saved at its coordinate
+    /// - `Event::TileContentUpdated(tile, coordinate)`: the updated tile is saved at its coordinate
+    ///
+    /// and ignores every other event, as well as coordinates outside of the map
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            | Event::Moved(tile, (row, col)) | Event::TileContentUpdated(tile, (row, col)) => {
+                if let Some(local) = self.to_local(ChartedCoordinate(*row, *col)) {
+                    self.map[local.0][local.1] = Some(tile.clone());
+                    self.stale.remove(&local);
+                }
+            }
+            | _ => {}
+        }
+    }
+
     /// resets the map to be exactly like the one available via `robot_map`
     ///
     /// it is advisable to call this function at the end of the game loop to reset any changes made, as well as to update the map to
     /// any new discoveries made in the past tick
+    ///
+    /// if the charted map has not been initialized yet, or its size differs from the world one, it is replaced as a whole.
+    /// If the map cannot be obtained it returns `LibError::OutOfBounds` and the current map is left untouched
     pub fn update_overwrite(&mut self, world: &World) -> Result<(), LibError> {
-        let map = robot_map(world);
+        self.overwrite_from(robot_map(world))
+    }
+
+    fn overwrite_from(&mut self, map: Option<Vec<Vec<Option<Tile>>>>) -> Result<(), LibError> {
         if map.is_some() {
             let map = map.unwrap();
+            self.stale.clear();
+            if (self.rows, self.cols) != shape(&map)
+                || self.map.len() != map.len()
+                || self.map.iter().zip(map.iter()).any(|(mine, theirs)| mine.len() != theirs.len())
+            {
+                (self.rows, self.cols) = shape(&map);
+                self.map = map;
+                self.offset = ChartedCoordinate::default();
+                return Ok(());
+            }
             for (i, row) in map.iter().enumerate() {
                 for (j, tile) in row.iter().enumerate() {
                     if tile.is_some() && self.map[i][j] != map[i][j] {
@@ -223,4 +717,1016 @@ impl ChartedWorld {
             Err(LibError::OutOfBounds)
         }
     }
+
+    /// copies the discovered tiles inside the box with the two provided coordinates as opposite corners (both included)
+    /// from the map currently obtainable via `robot_map()`, returning how many tiles actually changed.
+    ///
+    /// tiles that are None in the world map are left as they are. It returns `LibError::OutOfBounds` if the map cannot be
+    /// obtained or if any corner falls outside of either map, in which case no tile is updated
+    pub fn ingest_region(&mut self, world: &World, region: (ChartedCoordinate, ChartedCoordinate)) -> Result<usize, LibError> {
+        self.ingest_region_from(robot_map(world), region)
+    }
+
+    fn ingest_region_from(
+        &mut self,
+        map: Option<Vec<Vec<Option<Tile>>>>,
+        region: (ChartedCoordinate, ChartedCoordinate),
+    ) -> Result<usize, LibError> {
+        let map = map.ok_or(LibError::OutOfBounds)?;
+        let (a, b) = region;
+        let outside = |c: ChartedCoordinate| self.to_local(c).is_none() || cell(&map, c).is_none();
+        if outside(a) || outside(b) {
+            return Err(LibError::OutOfBounds);
+        }
+
+        let mut updated = 0;
+        for i in a.0.min(b.0)..=a.0.max(b.0) {
+            for j in a.1.min(b.1)..=a.1.max(b.1) {
+                let coordinate = ChartedCoordinate(i, j);
+                let (tile, local) = match (cell(&map, coordinate), self.to_local(coordinate)) {
+                    | (Some(Some(tile)), Some(local)) => (tile, local),
+                    | _ => continue,
+                };
+                self.stale.remove(&local);
+                if self.map[local.0][local.1].as_ref() != Some(tile) {
+                    self.map[local.0][local.1] = Some(tile.clone());
+                    updated += 1;
+                }
+            }
+        }
+        Ok(updated)
+    }
+
+    /// returns a copy of the current state of the map, which can later be given to `restore`
+    /// to revert any `set` made after this call
+    ///
+    /// ## Example
+    ///
+    ///     let snapshot = charted_world.snapshot();
+    ///     charted_world.set_overwrite(&my_tile, ChartedCoordinate(1, 2));
+    ///     // not a good idea after all
+    ///     charted_world.restore(snapshot);
+    pub fn snapshot(&self) -> ChartedWorldSnapshot {
+        ChartedWorldSnapshot { map: self.map.clone(), rows: self.rows, cols: self.cols, offset: self.offset }
+    }
+
+    /// reverts the map to the state saved in the provided snapshot
+    pub fn restore(&mut self, snapshot: ChartedWorldSnapshot) {
+        self.map = snapshot.map;
+        self.rows = snapshot.rows;
+        self.cols = snapshot.cols;
+        self.offset = snapshot.offset;
+    }
+
+    /// shrinks the stored map to the smallest box containing every discovered tile, freeing the memory
+    /// used by the undiscovered part of a big world. It returns the world coordinate of the new (0, 0),
+    /// which is also what `to_world_coordinate` adds to translate coordinates back.
+    ///
+    /// after compacting, every function works on the local coordinates of the smaller map (annotations and stale marks
+    /// are moved accordingly), and the undo history is cleared. The functions that read from the world
+    /// (`update`, `update_viewed`, `update_discover`, `ingest_region` and `handle_event`) keep taking world coordinates
+    /// and translate them, ignoring or rejecting as out of bounds the tiles outside of the smaller map:
+    /// `init` or `update_overwrite` bring back the full map. If nothing has been discovered the map is left as it is
+    pub fn compact(&mut self) -> ChartedCoordinate {
+        let (top_left, bottom_right) = match self.discovered_bounds() {
+            | None => return self.offset,
+            | Some(bounds) => bounds,
+        };
+        let (top, left) = (top_left.0, top_left.1);
+        let (height, width) = (bottom_right.0 - top + 1, bottom_right.1 - left + 1);
+
+        self.map = (top..top + height)
+            .map(|i| (left..left + width).map(|j| cell(&self.map, ChartedCoordinate(i, j)).cloned().flatten()))
+            .map(|row| row.collect())
+            .collect();
+        self.rows = height;
+        self.cols = width;
+        self.offset = ChartedCoordinate(self.offset.0 + top, self.offset.1 + left);
+        self.annotations = self
+            .annotations
+            .drain()
+            .filter(|(c, _)| c.0 >= top && c.1 >= left && c.0 < top + height && c.1 < left + width)
+            .map(|(c, label)| (ChartedCoordinate(c.0 - top, c.1 - left), label))
+            .collect();
+        self.stale = self
+            .stale
+            .drain()
+            .filter(|c| c.0 >= top && c.1 >= left && c.0 < top + height && c.1 < left + width)
+            .map(|c| ChartedCoordinate(c.0 - top, c.1 - left))
+            .collect();
+        self.history.clear();
+        self.undone.clear();
+        self.offset
+    }
+
+    /// translates a coordinate of this map into the world one, which differs only after a `compact`
+    pub fn to_world_coordinate(&self, local: ChartedCoordinate) -> ChartedCoordinate {
+        ChartedCoordinate(local.0 + self.offset.0, local.1 + self.offset.1)
+    }
+
+    // the opposite of to_world_coordinate, None if the world coordinate falls outside of the map
+    fn to_local(&self, world: ChartedCoordinate) -> Option<ChartedCoordinate> {
+        let local = ChartedCoordinate(world.0.checked_sub(self.offset.0)?, world.1.checked_sub(self.offset.1)?);
+        if self.check_bounds(local) { Some(local) } else { None }
+    }
+
+    /// attaches a label of your choice (like "visited", "dangerous" or "goal") to the specified coordinate,
+    /// replacing the previous one if any. Labels are independent from the tiles: they stay even if the tile changes.
+    ///
+    /// it returns `LibError::OutOfBounds` if the coordinate is invalid
+    pub fn annotate(&mut self, coordinate: ChartedCoordinate, label: &str) -> Result<(), LibError> {
+        if !self.check_bounds(coordinate) { return Err(LibError::OutOfBounds); }
+        self.annotations.insert(coordinate, label.to_string());
+        Ok(())
+    }
+
+    /// returns the label attached to the specified coordinate, if any
+    pub fn annotation(&self, coordinate: ChartedCoordinate) -> Option<&str> {
+        self.annotations.get(&coordinate).map(|label| label.as_str())
+    }
+
+    /// removes every label attached via `annotate`
+    pub fn clear_annotations(&mut self) {
+        self.annotations.clear();
+    }
+
+    /// marks the tile at the specified coordinate as stale: its content may have changed since it was discovered,
+    /// so it should be discovered again (for example with `ChartingBot::discover_line_force`).
+    ///
+    /// the mark is removed as soon as the tile is refreshed by any of the update functions or by `handle_event`.
+    /// It returns `LibError::OutOfBounds` if the coordinate is invalid
+    pub fn mark_stale(&mut self, coordinate: ChartedCoordinate) -> Result<(), LibError> {
+        if !self.check_bounds(coordinate) { return Err(LibError::OutOfBounds); }
+        self.stale.insert(coordinate);
+        Ok(())
+    }
+
+    /// returns the tiles marked via `mark_stale` that have not been refreshed yet, sorted by row and then by column
+    pub fn stale_tiles(&self) -> Vec<ChartedCoordinate> {
+        let mut stale: Vec<ChartedCoordinate> = self.stale.iter().copied().collect();
+        stale.sort_by_key(|c| (c.0, c.1));
+        stale
+    }
+
+    /// returns the frontier of the explored area: the discovered tiles with at least one orthogonal neighbour
+    /// that has not been discovered yet, sorted by row and then by column.
+    ///
+    /// these are the tiles the robot can reach to discover something new, see `ChartedPaths::reachable_frontier`
+    pub fn frontier(&self) -> Vec<ChartedCoordinate> {
+        let mut frontier = Vec::new();
+        for (i, row) in self.map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                if tile.is_none() {
+                    continue;
+                }
+                let unknown = |c: ChartedCoordinate| cell(&self.map, c).map_or(true, |t| t.is_none());
+                let unknown_around = (i > 0 && unknown(ChartedCoordinate(i - 1, j)))
+                    || (i + 1 < self.map.len() && unknown(ChartedCoordinate(i + 1, j)))
+                    || (j > 0 && row[j - 1].is_none())
+                    || (j + 1 < row.len() && row[j + 1].is_none());
+                if unknown_around {
+                    frontier.push(ChartedCoordinate(i, j));
+                }
+            }
+        }
+        frontier
+    }
+
+    /// returns the contents of the tile at the specified coordinate and of its discovered orthogonal neighbours
+    /// (in the order: the tile itself, up, down, left, right), leaving out tiles without any content.
+    ///
+    /// it is empty if the coordinate is out of bounds
+    pub fn collectible_nearby(&self, from: ChartedCoordinate) -> Vec<(ChartedCoordinate, Content)> {
+        if !self.check_bounds(from) {
+            return Vec::new();
+        }
+        let mut around = vec![from];
+        if from.0 > 0 { around.push(ChartedCoordinate(from.0 - 1, from.1)); }
+        around.push(ChartedCoordinate(from.0 + 1, from.1));
+        if from.1 > 0 { around.push(ChartedCoordinate(from.0, from.1 - 1)); }
+        around.push(ChartedCoordinate(from.0, from.1 + 1));
+
+        around
+            .into_iter()
+            .filter_map(|c| match self.get_ref(c) {
+                | Ok(Some(tile)) if tile.content != Content::None => Some((c, tile.content.clone())),
+                | _ => None,
+            })
+            .collect()
+    }
+
+    /// renders the map as ASCII art, one line per row, using the default palette:
+    ///
+    /// | tile type    | char |   | tile type    | char |
+    /// |--------------|------|---|--------------|------|
+    /// | DeepWater    | `~`  |   | Mountain     | `^`  |
+    /// | ShallowWater | `-`  |   | Snow         | `*`  |
+    /// | Sand         | `:`  |   | Lava         | `!`  |
+    /// | Grass        | `.`  |   | Teleport     | `T`  |
+    /// | Street       | `=`  |   | Wall         | `#`  |
+    /// | Hill         | `n`  |   | undiscovered | ` `  |
+    pub fn to_ascii(&self) -> String {
+        self.to_ascii_with_palette(&HashMap::new())
+    }
+
+    /// same as `to_ascii`, but the character of every tile type in the palette is the one provided,
+    /// while the types not in the palette keep the default one
+    ///
+    /// ## Example
+    ///
+    ///     let palette = HashMap::from([(TileType::Grass, ','), (TileType::Street, '#')]);
+    ///     println!("{}", charted_world.to_ascii_with_palette(&palette));
+    pub fn to_ascii_with_palette(&self, palette: &HashMap<TileType, char>) -> String {
+        let mut s = String::new();
+        for row in self.map.iter() {
+            for tile in row.iter() {
+                s.push(match tile {
+                    | None => ' ',
+                    | Some(tile) => {
+                        palette.get(&tile.tile_type).copied().unwrap_or(ChartedWorld::default_char(&tile.tile_type))
+                    }
+                });
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// same as `to_ascii`, but the tiles of the provided path (like the one returned by `ChartedPaths::shortest_path`)
+    /// are drawn as `@`, to check on the real terrain whether a computed route makes sense.
+    /// `*` is not used since it already stands for Snow; coordinates of the path outside of the map are ignored
+    ///
+    /// ## Example
+    ///
+    ///     if let Some((_, path)) = charted_paths.shortest_path(my_coordinate, destination) {
+    ///         println!("{}", charted_world.to_ascii_with_path(&path));
+    ///     }
+    pub fn to_ascii_with_path(&self, path: &[ChartedCoordinate]) -> String {
+        let mut lines: Vec<Vec<char>> = self.to_ascii().lines().map(|line| line.chars().collect()).collect();
+        for c in path.iter() {
+            if let Some(ch) = lines.get_mut(c.0).and_then(|line| line.get_mut(c.1)) {
+                *ch = '@';
+            }
+        }
+        lines.into_iter().map(|line| line.into_iter().collect::<String>() + "\n").collect()
+    }
+
+    fn default_char(tile_type: &TileType) -> char {
+        match tile_type {
+            | TileType::DeepWater => '~',
+            | TileType::ShallowWater => '-',
+            | TileType::Sand => ':',
+            | TileType::Grass => '.',
+            | TileType::Street => '=',
+            | TileType::Hill => 'n',
+            | TileType::Mountain => '^',
+            | TileType::Snow => '*',
+            | TileType::Lava => '!',
+            | TileType::Teleport(_) => 'T',
+            | TileType::Wall => '#',
+        }
+    }
+
+    /// returns how many discovered tiles contain the provided content.
+    ///
+    /// contents are compared by their default form, so the quantity is ignored:
+    /// `Content::Water(3)` matches every tile containing any amount of water
+    pub fn count_content(&self, content: &Content) -> usize {
+        let content = content.to_default();
+        self.map
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|tile| tile.content.to_default() == content)
+            .count()
+    }
+
+    /// returns the coordinates of all the discovered tiles containing the provided content,
+    /// compared the same way as in `count_content`
+    pub fn find_content(&self, content: &Content) -> Vec<ChartedCoordinate> {
+        let content = content.to_default();
+        let mut found = Vec::new();
+        for (i, row) in self.map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                if let Some(tile) = tile {
+                    if tile.content.to_default() == content {
+                        found.push(ChartedCoordinate::new(i, j));
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// returns the coordinates of all the discovered tiles of the provided type, in row order
+    pub fn coordinates_of_type(&self, tile_type: TileType) -> Vec<ChartedCoordinate> {
+        self.iter()
+            .filter(|(_, tile)| tile.as_ref().map_or(false, |tile| tile.tile_type == tile_type))
+            .map(|(c, _)| c)
+            .collect()
+    }
+
+    /// returns the edge of the discovered region containing `seed`: the region is made of all the discovered tiles
+    /// connected to it through orthogonal steps, and its edge of the tiles of the region with at least one orthogonal
+    /// neighbour that is undiscovered or outside of the map. Useful to plan patrols or walls around the explored area.
+    ///
+    /// the tiles are sorted by row and then by column; the result is empty if `seed` is not a discovered tile
+    pub fn region_perimeter(&self, seed: ChartedCoordinate) -> Vec<ChartedCoordinate> {
+        let known = |c: ChartedCoordinate| cell(&self.map, c).map_or(false, |tile| tile.is_some());
+        if !self.check_bounds(seed) || !known(seed) {
+            return Vec::new();
+        }
+        let mut region = HashSet::from([seed]);
+        let mut queue = VecDeque::from([seed]);
+        let mut perimeter = Vec::new();
+        while let Some(c) = queue.pop_front() {
+            let neighbours = [
+                c.0.checked_sub(1).map(|r| ChartedCoordinate(r, c.1)),
+                Some(ChartedCoordinate(c.0 + 1, c.1)),
+                c.1.checked_sub(1).map(|col| ChartedCoordinate(c.0, col)),
+                Some(ChartedCoordinate(c.0, c.1 + 1)),
+            ];
+            let mut on_edge = false;
+            for neighbour in neighbours {
+                match neighbour.filter(|n| self.check_bounds(*n) && known(*n)) {
+                    | None => on_edge = true,
+                    | Some(n) => {
+                        if region.insert(n) {
+                            queue.push_back(n);
+                        }
+                    }
+                }
+            }
+            if on_edge {
+                perimeter.push(c);
+            }
+        }
+        perimeter.sort_by_key(|c| (c.0, c.1));
+        perimeter
+    }
+
+    /// returns, for every tile of the map, the number of steps to the closest tile containing the provided content
+    /// (compared the same way as in `count_content`), so that the robot can reach a resource by always moving to
+    /// the neighbour with the smallest value, without running a search at every step.
+    ///
+    /// steps only go through discovered walkable tiles (the tiles holding the content are always 0, whatever
+    /// their type): cells that are not discovered, or that cannot reach the content, are `None`
+    pub fn distance_to_content(&self, content: &Content) -> Vec<Vec<Option<usize>>> {
+        let mut distances: Vec<Vec<Option<usize>>> = vec![vec![None; self.cols]; self.rows];
+        let mut queue: VecDeque<ChartedCoordinate> =
+            self.find_content(content).into_iter().filter(|c| c.0 < self.rows && c.1 < self.cols).collect();
+        for c in queue.iter() {
+            distances[c.0][c.1] = Some(0);
+        }
+        while let Some(c) = queue.pop_front() {
+            let distance = distances[c.0][c.1].unwrap_or(0);
+            let neighbours = [(c.0.wrapping_sub(1), c.1), (c.0 + 1, c.1), (c.0, c.1.wrapping_sub(1)), (c.0, c.1 + 1)];
+            for (i, j) in neighbours {
+                if i >= self.rows || j >= self.cols || distances[i][j].is_some() {
+                    continue;
+                }
+                match cell(&self.map, ChartedCoordinate(i, j)) {
+                    | Some(Some(tile)) if tile.tile_type.properties().walk() => {
+                        distances[i][j] = Some(distance + 1);
+                        queue.push_back(ChartedCoordinate(i, j));
+                    }
+                    | _ => {}
+                }
+            }
+        }
+        distances
+    }
+
+    /// returns the elevation of every tile of the map, `None` where the tile has not been discovered yet
+    pub fn elevation_grid(&self) -> Vec<Vec<Option<usize>>> {
+        self.map
+            .iter()
+            .map(|row| row.iter().map(|tile| tile.as_ref().map(|t| t.elevation)).collect())
+            .collect()
+    }
+
+    /// returns the coordinate and elevation of the highest discovered tile,
+    /// or `None` if nothing has been discovered yet.
+    ///
+    /// if several tiles share the same elevation, the first one in row order is returned
+    pub fn max_elevation(&self) -> Option<(ChartedCoordinate, usize)> {
+        let mut max: Option<(ChartedCoordinate, usize)> = None;
+        for (i, row) in self.map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                if let Some(tile) = tile {
+                    match max {
+                        | Some((_, elevation)) if elevation >= tile.elevation => {}
+                        | _ => max = Some((ChartedCoordinate::new(i, j), tile.elevation)),
+                    }
+                }
+            }
+        }
+        max
+    }
+
+    /// returns the coordinate and elevation of the lowest discovered tile,
+    /// or `None` if nothing has been discovered yet.
+    ///
+    /// if several tiles share the same elevation, the first one in row order is returned
+    pub fn min_elevation(&self) -> Option<(ChartedCoordinate, usize)> {
+        let mut min: Option<(ChartedCoordinate, usize)> = None;
+        for (i, row) in self.map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                if let Some(tile) = tile {
+                    match min {
+                        | Some((_, elevation)) if elevation <= tile.elevation => {}
+                        | _ => min = Some((ChartedCoordinate::new(i, j), tile.elevation)),
+                    }
+                }
+            }
+        }
+        min
+    }
+
+    /// returns the statistics of the map, see `WorldStats`.
+    ///
+    /// they are computed in a single pass, which is cheaper than calling `count_discovered`, `discovered_bounds`,
+    /// `min_elevation` and `max_elevation` one after the other
+    pub fn stats(&self) -> WorldStats {
+        let mut stats = WorldStats {
+            discovered: 0,
+            explored_percentage: 0.0,
+            tile_types: Vec::new(),
+            contents: Vec::new(),
+            min_elevation: None,
+            max_elevation: None,
+            bounds: None,
+        };
+        for (i, row) in self.map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                let tile = match tile {
+                    | None => continue,
+                    | Some(tile) => tile,
+                };
+                stats.discovered += 1;
+                match stats.tile_types.iter_mut().find(|(t, _)| *t == tile.tile_type) {
+                    | Some((_, count)) => *count += 1,
+                    | None => stats.tile_types.push((tile.tile_type, 1)),
+                }
+                let content = tile.content.to_default();
+                match stats.contents.iter_mut().find(|(c, _)| *c == content) {
+                    | Some((_, count)) => *count += 1,
+                    | None => stats.contents.push((content, 1)),
+                }
+                stats.min_elevation = Some(stats.min_elevation.map_or(tile.elevation, |e| e.min(tile.elevation)));
+                stats.max_elevation = Some(stats.max_elevation.map_or(tile.elevation, |e| e.max(tile.elevation)));
+                stats.bounds = match stats.bounds {
+                    | None => Some((ChartedCoordinate(i, j), ChartedCoordinate(i, j))),
+                    | Some((top_left, bottom_right)) => Some((
+                        ChartedCoordinate(top_left.0.min(i), top_left.1.min(j)),
+                        ChartedCoordinate(bottom_right.0.max(i), bottom_right.1.max(j)),
+                    )),
+                };
+            }
+        }
+        if self.rows * self.cols > 0 {
+            stats.explored_percentage = stats.discovered as f64 * 100.0 / (self.rows * self.cols) as f64;
+        }
+        stats
+    }
+
+    /// returns a hash of the current state of the map, computed over the size of the map and, for every
+    /// tile, its type, its content (in default form, so quantities are ignored) and its elevation.
+    ///
+    /// equal maps always produce the same value within the same build, so comparing the hashes of two ticks is a
+    /// cheap way to know if anything changed. Types and contents are hashed through robotics_lib's own `Hash`
+    /// implementations, so the value is not meant to be stored and compared with one computed by a different
+    /// version of this crate or of robotics_lib.
+    ///
+    /// **NB**: this is a plain 64 bit hash and not a cryptographic digest: different maps may,
+    /// although rarely, produce the same value
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = StableHasher(0xcbf29ce484222325);
+        self.rows.hash(&mut hasher);
+        self.cols.hash(&mut hasher);
+        for tile in self.map.iter().flatten() {
+            match tile {
+                | None => 0u8.hash(&mut hasher),
+                | Some(tile) => {
+                    1u8.hash(&mut hasher);
+                    tile.tile_type.hash(&mut hasher);
+                    tile.content.to_default().hash(&mut hasher);
+                    tile.elevation.hash(&mut hasher);
+                }
+            }
+        }
+        hasher.finish()
+    }
+
+    /// returns the first coordinate, in row-major order, at which the two maps differ, or `None` if they are identical.
+    /// It stops at the first difference, so it is much cheaper than `diff_patch` to know whether anything changed.
+    ///
+    /// if the two maps have different sizes, a cell that exists only in the bigger one counts as a difference
+    pub fn first_difference(&self, other: &ChartedWorld) -> Option<ChartedCoordinate> {
+        let len = self.map.len().max(other.map.len());
+        for i in 0..len {
+            let (mine, theirs) = (self.map.get(i), other.map.get(i));
+            let width = mine.map_or(0, |row| row.len()).max(theirs.map_or(0, |row| row.len()));
+            for j in 0..width {
+                if mine.and_then(|row| row.get(j)) != theirs.and_then(|row| row.get(j)) {
+                    return Some(ChartedCoordinate(i, j));
+                }
+            }
+        }
+        None
+    }
+
+    /// returns the list of changes that turn this map into `other`, as pairs of coordinate and
+    /// the value `other` holds there (`None` meaning the tile has to be forgotten).
+    ///
+    /// together with `apply_patch` it allows to share only what changed between two maps,
+    /// instead of the whole grid. The two maps are expected to have the same size: the patch only covers
+    /// the coordinates of `other`, so any change outside of this map will be rejected by `apply_patch`
+    pub fn diff_patch(&self, other: &ChartedWorld) -> Vec<(ChartedCoordinate, Option<Tile>)> {
+        let mut patch = Vec::new();
+        for (i, row) in other.map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                let current = self.map.get(i).and_then(|r| r.get(j));
+                if current != Some(tile) {
+                    patch.push((ChartedCoordinate::new(i, j), tile.clone()));
+                }
+            }
+        }
+        patch
+    }
+
+    /// applies a patch produced by `diff_patch`.
+    ///
+    /// it returns `LibError::OutOfBounds` if any of the coordinates is invalid; in that case
+    /// the map is left untouched
+    pub fn apply_patch(&mut self, patch: &[(ChartedCoordinate, Option<Tile>)]) -> Result<(), LibError> {
+        if patch.iter().any(|(coordinate, _)| !self.check_bounds(*coordinate)) {
+            return Err(LibError::OutOfBounds);
+        }
+        for (coordinate, tile) in patch.iter() {
+            self.map[coordinate.0][coordinate.1] = tile.clone();
+        }
+        Ok(())
+    }
+
+    /// returns true if the two maps have the same size and, at every coordinate, either both tiles
+    /// are undiscovered or they have the same type and content. Elevation is not taken into account
+    pub fn equivalent_ignoring_elevation(&self, other: &ChartedWorld) -> bool {
+        (self.rows, self.cols) == (other.rows, other.cols)
+            && self
+                .map
+                .iter()
+                .zip(other.map.iter())
+                .all(|(a, b)| a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| ChartedWorld::same_terrain(a, b)))
+    }
+
+    /// same as `equivalent_ignoring_elevation`, but only for the tile at the specified coordinate.
+    ///
+    /// it returns `LibError::OutOfBounds` if the coordinate is invalid for either map
+    pub fn equivalent_at(&self, other: &ChartedWorld, coordinate: ChartedCoordinate) -> Result<bool, LibError> {
+        if !self.check_bounds(coordinate) || !other.check_bounds(coordinate) {
+            return Err(LibError::OutOfBounds);
+        }
+        Ok(ChartedWorld::same_terrain(
+            &self.map[coordinate.0][coordinate.1],
+            &other.map[coordinate.0][coordinate.1],
+        ))
+    }
+
+    fn same_terrain(a: &Option<Tile>, b: &Option<Tile>) -> bool {
+        match (a, b) {
+            | (None, None) => true,
+            | (Some(a), Some(b)) => a.tile_type == b.tile_type && a.content == b.content,
+            | _ => false,
+        }
+    }
+
+    /// tells whether the straight line between the two coordinates is unobstructed: it is not if any tile strictly
+    /// between them has one of the blocking types, or has not been discovered yet.
+    /// The two ends are not checked, and out of bounds coordinates never see each other
+    pub fn line_of_sight(&self, from: ChartedCoordinate, to: ChartedCoordinate, blocks: &[TileType]) -> bool {
+        self.line_of_sight_with(from, to, blocks, true)
+    }
+
+    /// same as `line_of_sight`, but undiscovered tiles block the line only if `unknown_blocks` is true
+    pub fn line_of_sight_with(
+        &self,
+        from: ChartedCoordinate,
+        to: ChartedCoordinate,
+        blocks: &[TileType],
+        unknown_blocks: bool,
+    ) -> bool {
+        if !self.check_bounds(from) || !self.check_bounds(to) {
+            return false;
+        }
+        let line = ChartedCoordinate::bresenham_line(&from, &to);
+        line.iter().skip(1).take(line.len().saturating_sub(2)).all(|c| match cell(&self.map, *c) {
+            | None | Some(None) => !unknown_blocks,
+            | Some(Some(tile)) => !blocks.contains(&tile.tile_type),
+        })
+    }
+
+    /// scans the rectangular region between the two provided corners (top-left and bottom-right, both included)
+    /// and saves into a new ChartedMap every discovered tile matching the provided key, for example every tile
+    /// containing a `Content::Coin` in the area around the robot. Parts of the region outside the map are ignored.
+    ///
+    /// the returned ChartedMap is a charting tool like any other, so it is obtained through `ChartingTools::tool`
+    /// and counts toward the limit of tools alive at the same time: it fails with the same error when the limit is reached
+    ///
+    /// ## Example
+    ///
+    ///     let coins = charted_world
+    ///         .index_content_in_region((ChartedCoordinate(0, 0), ChartedCoordinate(10, 10)), &Content::Coin(0))
+    ///         .expect("too many tools used!");
+    pub fn index_content_in_region<K: MapKey>(
+        &self,
+        region: (ChartedCoordinate, ChartedCoordinate),
+        key_filter: &K,
+    ) -> Result<ChartedMap<K>, u8> {
+        let mut charted_map = ChartingTools::tool::<ChartedMap<K>>()?;
+        if self.rows == 0 || self.cols == 0 {
+            return Ok(charted_map);
+        }
+        let key = key_filter.to_default();
+        let (top_left, bottom_right) = region;
+        for i in top_left.0..=bottom_right.0.min(self.rows - 1) {
+            for j in top_left.1..=bottom_right.1.min(self.cols - 1) {
+                if let Some(Some(tile)) = cell(&self.map, ChartedCoordinate(i, j)) {
+                    let poi = <K as MapKey>::from(tile);
+                    if poi.to_default() == key {
+                        charted_map.save(&poi, &ChartedCoordinate::new(i, j));
+                    }
+                }
+            }
+        }
+        Ok(charted_map)
+    }
+
+    /// returns a central discovered walkable tile, a good place for a base of operations from which every
+    /// other known walkable tile is cheap to reach, or `None` if no walkable tile has been discovered.
+    ///
+    /// it is an approximation of the tile minimizing the total distance to all the others: the geometric
+    /// centroid of the discovered walkable tiles is computed and the walkable tile closest to it is returned
+    pub fn centroid_tile(&self) -> Option<ChartedCoordinate> {
+        let walkable: Vec<ChartedCoordinate> = self
+            .map
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| {
+                row.iter().enumerate().filter_map(move |(j, tile)| match tile {
+                    | Some(tile) if tile.tile_type.properties().walk() => Some(ChartedCoordinate(i, j)),
+                    | _ => None,
+                })
+            })
+            .collect();
+        if walkable.is_empty() {
+            return None;
+        }
+
+        let n = walkable.len() as f64;
+        let row = walkable.iter().map(|c| c.0 as f64).sum::<f64>() / n;
+        let col = walkable.iter().map(|c| c.1 as f64).sum::<f64>() / n;
+        let distance = |c: &ChartedCoordinate| (c.0 as f64 - row).powi(2) + (c.1 as f64 - col).powi(2);
+        walkable.into_iter().min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+}
+
+/// bounds-safe lookup of a cell of a map, None if the coordinate falls outside of it,
+/// whatever the shape of the map (even if it is not a square or has rows of different length)
+fn cell(map: &Vec<Vec<Option<Tile>>>, coordinate: ChartedCoordinate) -> Option<&Option<Tile>> {
+    map.get(coordinate.0).and_then(|row| row.get(coordinate.1))
+}
+
+/// number of rows and columns of a map, taking the length of the first row as the number of columns
+fn shape(map: &Vec<Vec<Option<Tile>>>) -> (usize, usize) {
+    (map.len(), map.first().map_or(0, |row| row.len()))
+}
+
+/// struct: SparseChartedWorld
+///
+/// same as ChartedWorld, but only the discovered tiles are stored, in a map indexed by coordinate:
+/// on huge worlds of which only a small part has been explored it uses far less memory than the dense grid.
+/// It offers the basic queries and changes of ChartedWorld (`init`, `at`, `set`, `set_overwrite`, `iter`,
+/// `count_discovered`), and can be converted to and from it with `From`, keeping the same slot
+/// among the active tools. The `compact` offset, the annotations and the stale marks are kept through the
+/// conversions as well, only the undo history is lost
+#[derive(Debug, Clone)]
+pub struct SparseChartedWorld {
+    map: HashMap<ChartedCoordinate, Tile>,
+    rows: usize,
+    cols: usize,
+    offset: ChartedCoordinate,
+    annotations: HashMap<ChartedCoordinate, String>,
+    stale: HashSet<ChartedCoordinate>,
+    slot: Slot,
+}
+
+impl Drop for SparseChartedWorld {
+    fn drop(&mut self) {
+        self.slot.release();
+    }
+}
+
+impl ChartingTool for SparseChartedWorld {}
+
+impl New for SparseChartedWorld {
+    fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+            rows: 0,
+            cols: 0,
+            offset: ChartedCoordinate::default(),
+            annotations: HashMap::new(),
+            stale: HashSet::new(),
+            slot: Slot::acquired(),
+        }
+    }
+}
+
+impl SparseChartedWorld {
+    /// initializes the map to the one currently obtainable from the world via `robot_map()`
+    ///
+    /// it returns `LibError::OutOfBounds` if the map cannot be obtained, in which case the current map is left untouched
+    pub fn init(&mut self, world: &World) -> Result<(), LibError> {
+        let map = robot_map(world).ok_or(LibError::OutOfBounds)?;
+        (self.rows, self.cols) = shape(&map);
+        self.map = SparseChartedWorld::sparse(map);
+        self.offset = ChartedCoordinate::default();
+        Ok(())
+    }
+
+    fn sparse(map: Vec<Vec<Option<Tile>>>) -> HashMap<ChartedCoordinate, Tile> {
+        let mut sparse = HashMap::new();
+        for (i, row) in map.into_iter().enumerate() {
+            for (j, tile) in row.into_iter().enumerate() {
+                if let Some(tile) = tile {
+                    sparse.insert(ChartedCoordinate(i, j), tile);
+                }
+            }
+        }
+        sparse
+    }
+
+    fn check_bounds(&self, coordinate: ChartedCoordinate) -> bool {
+        coordinate.0 < self.rows && coordinate.1 < self.cols
+    }
+
+    /// returns the tile at the specified coordinate, like `ChartedWorld::at`
+    pub fn at(&self, coordinate: ChartedCoordinate) -> Result<Option<Tile>, LibError> {
+        if !self.check_bounds(coordinate) { return Err(LibError::OutOfBounds); }
+        Ok(self.map.get(&coordinate).cloned())
+    }
+
+    /// sets the tile at the specified coordinate, like `ChartedWorld::set`: it fails if the tile
+    /// has already been set or discovered, or if the coordinates are invalid
+    pub fn set(&mut self, tile: &Tile, coordinate: ChartedCoordinate) -> Result<(), (LibError, Option<Tile>)> {
+        if !self.check_bounds(coordinate) { return Err((LibError::OutOfBounds, None)); }
+        match self.map.get(&coordinate) {
+            | Some(old_tile) => Err((LibError::OperationNotAllowed, Some(old_tile.clone()))),
+            | None => {
+                self.map.insert(coordinate, tile.clone());
+                Ok(())
+            }
+        }
+    }
+
+    /// sets the tile at the specified coordinate, like `ChartedWorld::set_overwrite`: it only fails if the
+    /// coordinates are invalid
+    pub fn set_overwrite(&mut self, tile: &Tile, coordinate: ChartedCoordinate) -> Result<(), LibError> {
+        if !self.check_bounds(coordinate) { return Err(LibError::OutOfBounds); }
+        self.map.insert(coordinate, tile.clone());
+        Ok(())
+    }
+
+    /// returns an iterator over the discovered tiles only, with their coordinates, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (ChartedCoordinate, &Tile)> + '_ {
+        self.map.iter().map(|(c, tile)| (*c, tile))
+    }
+
+    /// returns how many tiles of the map have been discovered or set
+    pub fn count_discovered(&self) -> usize {
+        self.map.len()
+    }
+}
+
+impl From<ChartedWorld> for SparseChartedWorld {
+    fn from(mut value: ChartedWorld) -> Self {
+        // the sparse world takes the slot of the dense one, which frees nothing when dropped at the end of the conversion
+        let slot = std::mem::replace(&mut value.slot, Slot::uncounted());
+        let map = std::mem::take(&mut value.map);
+        SparseChartedWorld {
+            map: SparseChartedWorld::sparse(map),
+            rows: value.rows,
+            cols: value.cols,
+            offset: value.offset,
+            annotations: std::mem::take(&mut value.annotations),
+            stale: std::mem::take(&mut value.stale),
+            slot,
+        }
+    }
+}
+
+impl From<SparseChartedWorld> for ChartedWorld {
+    fn from(mut value: SparseChartedWorld) -> Self {
+        // same as above, the other way around
+        let mut world = ChartedWorld::new();
+        world.slot = std::mem::replace(&mut value.slot, Slot::uncounted());
+        world.rows = value.rows;
+        world.cols = value.cols;
+        world.offset = value.offset;
+        world.annotations = std::mem::take(&mut value.annotations);
+        world.stale = std::mem::take(&mut value.stale);
+        world.map = vec![vec![None; value.cols]; value.rows];
+        for (c, tile) in std::mem::take(&mut value.map).into_iter() {
+            world.map[c.0][c.1] = Some(tile);
+        }
+        world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{grid, uncounted};
+
+    #[test]
+    fn missing_robot_map_keeps_the_previous_state() {
+        let mut cw = uncounted::<ChartedWorld>();
+        let map = grid(&["..~", ". T", "#^^"]);
+        assert!(cw.init_from(Some(map.clone())).is_ok());
+
+        assert!(matches!(cw.init_from(None), Err(LibError::OutOfBounds)));
+        let result = cw.update_from(None, &vec![ChartedCoordinate(0, 0), ChartedCoordinate(1, 1)]);
+        assert!(matches!(result, Err((LibError::OutOfBounds, ChartedCoordinate(0, 0)))));
+        assert!(matches!(cw.overwrite_from(None), Err(LibError::OutOfBounds)));
+        assert_eq!(cw.map, map);
+        assert_eq!((cw.rows, cw.cols), (3, 3));
+
+        // with a map the updates go through
+        let changed = grid(&["...", "..T", "#^^"]);
+        assert_eq!(cw.update_from(Some(changed.clone()), &vec![ChartedCoordinate(0, 2)]).unwrap().len(), 1);
+        assert!(cw.overwrite_from(Some(changed.clone())).is_ok());
+        assert_eq!(cw.map, changed);
+    }
+
+    /// xorshift generator, enough to spread the fuzz cases without an extra dependency
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn map(&mut self) -> Vec<Vec<Option<Tile>>> {
+            // rows of random length, so that ragged maps are tried too
+            let rows: Vec<String> = (0..self.below(6))
+                .map(|_| (0..self.below(6)).map(|_| "~-:.=n^*!T# ".as_bytes()[self.below(12)] as char).collect())
+                .collect();
+            grid(&rows.iter().map(|row| row.as_str()).collect::<Vec<&str>>())
+        }
+
+        fn coordinate(&mut self) -> ChartedCoordinate {
+            ChartedCoordinate(self.below(8), self.below(8))
+        }
+    }
+
+    #[test]
+    fn random_coordinates_and_sizes_never_panic() {
+        let mut random = Random(0x9e3779b97f4a7c15);
+        let tile = crate::test_utils::tile(TileType::Sand);
+        for _ in 0..500 {
+            let mut cw = uncounted::<ChartedWorld>();
+            let _ = cw.init_from(Some(random.map()));
+            let mut other = uncounted::<ChartedWorld>();
+            let _ = other.init_from(Some(random.map()));
+
+            for _ in 0..20 {
+                let (a, b) = (random.coordinate(), random.coordinate());
+                let _ = cw.at(a);
+                let _ = cw.get_ref(a);
+                let _ = cw.tile_type_at(a);
+                let _ = cw.content_at(a);
+                let _ = cw.at_mut(a);
+                let _ = cw.row(a.0);
+                let _ = cw.column(a.1);
+                let _ = cw.set(&tile, a);
+                let _ = cw.set_overwrite(&tile, b);
+                let _ = cw.set_multiple(&vec![(&tile, a), (&tile, b)]);
+                let _ = cw.set_multiple_overwrite(&vec![(tile.clone(), b), (tile.clone(), a)]);
+                let _ = cw.annotate(a, "fuzz");
+                let _ = cw.mark_stale(b);
+                let _ = cw.region_perimeter(a);
+                let _ = cw.collectible_nearby(b);
+                let _ = cw.line_of_sight(a, b, &[TileType::Wall]);
+                let _ = cw.equivalent_at(&other, a);
+                let _ = cw.apply_patch(&[(a, None), (b, Some(tile.clone()))]);
+                let _ = cw.update_from(Some(random.map()), &vec![a, b]);
+                let _ = cw.to_ascii_with_path(&[a, b]);
+                cw.handle_event(&Event::Moved(tile.clone(), (b.0, b.1)));
+                cw.undo();
+                cw.redo();
+            }
+            cw.merge(&other);
+            let _ = cw.first_difference(&other);
+            let _ = cw.diff_patch(&other);
+            let _ = cw.frontier();
+            let _ = cw.stats();
+            let _ = cw.overwrite_from(Some(random.map()));
+            cw.compact();
+            let _ = cw.to_ascii();
+        }
+    }
+
+    #[test]
+    fn coordinates_of_type_on_a_mixed_map() {
+        let mut cw = uncounted::<ChartedWorld>();
+        cw.init_from(Some(grid(&["^.~^", " ^T.", "~~ ^"]))).unwrap();
+        let mountains = [(0, 0), (0, 3), (1, 1), (2, 3)].map(ChartedCoordinate::from).to_vec();
+        assert_eq!(cw.coordinates_of_type(TileType::Mountain), mountains);
+        assert_eq!(cw.coordinates_of_type(TileType::Teleport(true)), vec![ChartedCoordinate(1, 2)]);
+        // teleports are told apart by their activation, undiscovered tiles are never returned
+        assert!(cw.coordinates_of_type(TileType::Teleport(false)).is_empty());
+        assert!(cw.coordinates_of_type(TileType::Lava).is_empty());
+        assert_eq!(cw.coordinates_of_type(TileType::DeepWater).len(), 3);
+    }
+
+    #[test]
+    fn rectangular_world() {
+        // two rows and four columns: swapping the two dimensions would accept (3, 1) and reject (1, 3)
+        let mut cw = uncounted::<ChartedWorld>();
+        cw.init_from(Some(grid(&["..~^", "T.:#"]))).unwrap();
+        assert_eq!((cw.rows, cw.cols), (2, 4));
+
+        assert_eq!(cw.tile_type_at(ChartedCoordinate(1, 3)).ok(), Some(Some(TileType::Wall)));
+        assert!(cw.at(ChartedCoordinate(3, 1)).is_err());
+        assert!(cw.at(ChartedCoordinate(2, 0)).is_err());
+        assert!(cw.at(ChartedCoordinate(0, 4)).is_err());
+        assert_eq!(cw.row(1).map(|row| row.len()).ok(), Some(4));
+        assert!(cw.row(2).is_err());
+        assert_eq!(cw.column(3).map(|column| column.len()).ok(), Some(2));
+        assert!(cw.column(4).is_err());
+
+        let sand = crate::test_utils::tile(TileType::Sand);
+        assert!(cw.set_overwrite(&sand, ChartedCoordinate(1, 3)).is_ok());
+        assert!(cw.set_overwrite(&sand, ChartedCoordinate(3, 1)).is_err());
+        assert_eq!(cw.to_ascii().lines().map(|line| line.chars().count()).collect::<Vec<_>>(), vec![4, 4]);
+    }
+
+    #[test]
+    fn sparse_round_trip_keeps_offset_annotations_and_stale_marks() {
+        let mut cw = uncounted::<ChartedWorld>();
+        cw.init_from(Some(grid(&["   ", " .^", " :."]))).unwrap();
+        cw.compact();
+        cw.annotate(ChartedCoordinate(0, 1), "goal").unwrap();
+        cw.mark_stale(ChartedCoordinate(1, 0)).unwrap();
+
+        let sparse = SparseChartedWorld::from(cw.clone());
+        assert_eq!(sparse.count_discovered(), 4);
+        let back = ChartedWorld::from(sparse);
+        assert_eq!(back.to_world_coordinate(ChartedCoordinate(0, 0)), ChartedCoordinate(1, 1));
+        assert_eq!(back.annotation(ChartedCoordinate(0, 1)), Some("goal"));
+        assert_eq!(back.stale_tiles(), vec![ChartedCoordinate(1, 0)]);
+        assert_eq!(back.to_ascii(), cw.to_ascii());
+    }
+
+    #[test]
+    fn world_coordinates_after_compact() {
+        let mut cw = uncounted::<ChartedWorld>();
+        cw.init_from(Some(grid(&["    ", " .. ", " .  ", "    "]))).unwrap();
+        assert_eq!(cw.compact(), ChartedCoordinate(1, 1));
+        let seen = grid(&["....", ".^..", ".:#.", "...."]);
+
+        // the world coordinates are translated to the ones of the smaller map
+        cw.handle_event(&Event::Moved(crate::test_utils::tile(TileType::Sand), (2, 2)));
+        assert_eq!(cw.tile_type_at(ChartedCoordinate(1, 1)).ok(), Some(Some(TileType::Sand)));
+        assert_eq!(cw.update_from(Some(seen.clone()), &vec![ChartedCoordinate(1, 1)]).map(|c| c.len()).ok(), Some(1));
+        assert_eq!(cw.tile_type_at(ChartedCoordinate(0, 0)).ok(), Some(Some(TileType::Mountain)));
+        let region = (ChartedCoordinate(2, 1), ChartedCoordinate(2, 2));
+        assert_eq!(cw.ingest_region_from(Some(seen.clone()), region).ok(), Some(2));
+        assert_eq!(cw.tile_type_at(ChartedCoordinate(1, 1)).ok(), Some(Some(TileType::Wall)));
+
+        // and the ones outside of it are left out
+        assert!(cw.update_from(Some(seen.clone()), &vec![ChartedCoordinate(0, 0)]).is_err());
+        assert!(cw.ingest_region_from(Some(seen), (ChartedCoordinate(0, 0), ChartedCoordinate(1, 1))).is_err());
+        cw.handle_event(&Event::Moved(crate::test_utils::tile(TileType::Sand), (3, 3)));
+        assert_eq!((cw.rows, cw.cols), (2, 2));
+        assert_eq!(cw.tile_type_at(ChartedCoordinate(1, 1)).ok(), Some(Some(TileType::Wall)));
+    }
+
+    #[test]
+    fn ingest_region_of_a_rectangular_world() {
+        let mut cw = uncounted::<ChartedWorld>();
+        cw.init_from(Some(grid(&["    ", "    "]))).unwrap();
+        let seen = grid(&["..~^", "T.:#"]);
+
+        // the far corner is (1, 3), the one with the dimensions swapped is outside of both maps
+        let region = (ChartedCoordinate(0, 1), ChartedCoordinate(1, 3));
+        assert_eq!(cw.ingest_region_from(Some(seen.clone()), region).ok(), Some(6));
+        assert_eq!(cw.tile_type_at(ChartedCoordinate(1, 3)).ok(), Some(Some(TileType::Wall)));
+        assert_eq!(cw.tile_type_at(ChartedCoordinate(0, 0)).ok(), Some(None));
+        assert!(cw.ingest_region_from(Some(seen), (ChartedCoordinate(0, 0), ChartedCoordinate(3, 1))).is_err());
+    }
 }
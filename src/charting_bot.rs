@@ -1,27 +1,72 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::usize;
 
 use robotics_lib::{
     interface::{discover_tiles, robot_map, Direction},
     runner::Runnable,
     utils::LibError,
-    world::World,
+    world::{tile::Tile, World},
 };
 
 use crate::charted_coordinate::ChartedCoordinate;
-use crate::{reserved::New, ChartingTool, NUMBER};
+use crate::charted_paths::ChartedPaths;
+use crate::{reserved::New, ChartingTool, Slot};
+
+/// Energy spent by `discover_tiles` for every tile discovered.
+const DISCOVERY_COST: usize = 3;
+
+/// How the discovery functions treat the tiles of a strip that fall outside of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderMode {
+    /// The strip is cut at the edges of the map, the tiles outside are ignored.
+    Clamp,
+    /// The map is treated as a torus: the strip continues from the opposite edge.
+    Wrap,
+}
+
+impl BorderMode {
+    /// Maps a row or column index to a valid one of a map of side `dim`, if any.
+    pub(crate) fn apply(&self, value: i64, dim: usize) -> Option<usize> {
+        if dim == 0 {
+            return None;
+        }
+        match self {
+            | BorderMode::Clamp => (value >= 0 && value < dim as i64).then_some(value as usize),
+            | BorderMode::Wrap => Some(value.rem_euclid(dim as i64) as usize),
+        }
+    }
+}
+
+/// Outcome of `ChartingBot::discover_tiles_retrying`: which of the requested tiles ended up in the robots
+/// personal map and which did not.
+#[derive(Debug, Default)]
+pub struct DiscoveryReport {
+    /// The tiles that were discovered, or were already known.
+    pub succeeded: Vec<ChartedCoordinate>,
+    /// The tiles that could not be discovered, including the ones never tried because the sweep gave up.
+    pub failed: Vec<ChartedCoordinate>,
+    /// The error that made the sweep give up, if any.
+    pub error: Option<LibError>,
+}
 
 #[derive(Debug, Clone)]
 pub struct ChartingBot {
     coordinates: ChartedCoordinate,
+    retries: usize,
+    max_tiles: Option<usize>,
+    slot: Slot,
 }
 
 impl Drop for ChartingBot {
     fn drop(&mut self) {
-        if let Ok(mut n) = NUMBER.lock() {
-            if *n > 0 {
-                *n = *n - 1;
-            }
-        }
+        self.slot.release();
+    }
+}
+
+impl Display for ChartingBot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChartingBot at ({})", self.coordinates)
     }
 }
 
@@ -30,6 +75,9 @@ impl New for ChartingBot {
     fn new() -> Self {
         ChartingBot {
             coordinates: ChartedCoordinate(0, 0),
+            retries: 0,
+            max_tiles: None,
+            slot: Slot::acquired(),
         }
     }
 }
@@ -37,9 +85,42 @@ impl New for ChartingBot {
 impl ChartingBot {
     /// # Initializes the ChartingBot
     /// The starting position of the bot is set to the position of the Robot.
+    ///
+    /// With the `log` feature enabled, the placement and every movement of the bot are logged at debug level
+    /// through the `log` crate, so that the user decides if and where they are printed.
     pub fn init(&mut self, robot: &impl Runnable) {
         self.coordinates = ChartedCoordinate::from(robot.get_coordinate());
-        //println!("DiscoveryBot placed in {:?}", self.coordinates)
+        #[cfg(feature = "log")]
+        log::debug!("ChartingBot placed in ({})", self.coordinates);
+    }
+
+    /// # Position of the ChartingBot
+    /// Returns the coordinate the bot is currently at, which changes while following a path.
+    pub fn position(&self) -> ChartedCoordinate {
+        self.coordinates
+    }
+
+    /// # Sets the discovery retries
+    /// Sets how many more times `discover_tiles_retrying` tries to discover a tile after a failed attempt,
+    /// before giving up on the sweep. The default is 0 (a single attempt).
+    pub fn set_retries(&mut self, retries: usize) {
+        self.retries = retries;
+    }
+
+    /// # Sets the discovery cap
+    /// Sets the maximum number of new tiles a single line discovery (`discover_line`, `discover_line_force`,
+    /// `discover_line_adaptive`, `discover_line_with_border` and `discover_toward`) can discover: once it is
+    /// reached the discovery stops and returns the count, even if the strip is larger. None (the default)
+    /// removes the cap.
+    ///
+    /// ## Notes
+    /// - The cap allows to pace the discovery across ticks without exhausting the tiles the world lets discover,
+    /// but it does not replace that limit: if the world runs out first `LibError::NoMoreDiscovery` is still returned.
+    /// - Tiles that are already present in the robots map do not count, unless they are discovered again by
+    /// `discover_line_force`.
+    /// - `discover_path` applies the cap to each of its steps.
+    pub fn set_max_tiles(&mut self, max_tiles: Option<usize>) {
+        self.max_tiles = max_tiles;
     }
 
     /// # Performs a line discovery
@@ -73,127 +154,223 @@ impl ChartingBot {
         width: usize,
         direction: Direction,
     ) -> Result<usize, LibError> {
-        let world_dim = robot_map(world).unwrap()[0].len();
-
-        let to_visit = match direction {
-            | Direction::Up => {
-                let iter_x;
-                if self.coordinates.get_col() < width / 2 {
-                    iter_x = 0..=self.coordinates.get_col() + width / 2;
-                } else {
-                    if self.coordinates.get_col() + width / 2 > world_dim {
-                        iter_x = self.coordinates.get_col() - width / 2..=world_dim - 1;
-                    } else {
-                        iter_x = self.coordinates.get_col() - width / 2..=self.coordinates.get_col() + width / 2;
-                    }
-                }
-                let iter_y;
-                let mut tiles: Vec<(usize, usize)> = vec![];
+        let to_visit = self.line_tiles(world, length, width, &direction);
+        self.discover_all(robot, world, to_visit, false)
+    }
 
-                if (self.coordinates.get_row() as i32 - length as i32) < 0 {
-                    iter_y = (0..=self.coordinates.get_row()).rev();
-                } else {
-                    iter_y = ((self.coordinates.get_row() - length as usize + 1)..=self.coordinates.get_row()).rev();
-                }
+    /// # Performs a forced line discovery
+    /// Same as `discover_line`, but tiles already present in the robots personal map are discovered
+    /// again (spending energy), to get a fresh read of tiles whose content may have changed since.
+    ///
+    /// # Returns
+    /// - The number of discovered tiles or an error.
+    pub fn discover_line_force(
+        &mut self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        length: usize,
+        width: usize,
+        direction: Direction,
+    ) -> Result<usize, LibError> {
+        let to_visit = self.line_tiles(world, length, width, &direction);
+        self.discover_all(robot, world, to_visit, true)
+    }
 
-                for y in iter_y {
-                    for x in iter_x.clone() {
-                        tiles.push((y, x))
-                    }
-                }
-                tiles
-            }
-            | Direction::Down => {
-                let inter_x;
-                let inter_y;
-                if self.coordinates.get_col() < width / 2 {
-                    inter_x = 0..=self.coordinates.get_col() + width / 2;
-                } else {
-                    if self.coordinates.get_col() + width / 2 >= world_dim {
-                        inter_x = self.coordinates.get_col() - width / 2..=world_dim - 1;
-                    } else {
-                        inter_x = self.coordinates.get_col() - width / 2..=self.coordinates.get_col() + width / 2;
-                    }
-                }
-                if (self.coordinates.get_row() + length as usize - 1usize) >= world_dim {
-                    inter_y = self.coordinates.get_row()..=world_dim - 1
-                } else {
-                    inter_y = self.coordinates.get_row()..=self.coordinates.get_row() + length as usize - 1usize
-                }
+    /// # Performs a line discovery fitting the energy
+    /// Same as `discover_line`, but the strip is made narrower until its estimated cost (see `estimate_energy`)
+    /// fits the current energy of the robot, so that scouting goes on when the full strip is not affordable.
+    ///
+    /// # Errors
+    /// `LibError::NotEnoughEnergy` if even a strip of width 1 costs more than the energy of the robot, otherwise
+    /// the same errors of `discover_line`.
+    ///
+    /// # Returns
+    /// - The number of discovered tiles or an error.
+    pub fn discover_line_adaptive(
+        &mut self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        length: usize,
+        max_width: usize,
+        direction: Direction,
+    ) -> Result<usize, LibError> {
+        let energy = robot.get_energy().get_energy_level();
+        let width = (1..=max_width.max(1))
+            .rev()
+            .find(|width| self.estimate_energy(world, length, *width, direction.clone()) <= energy)
+            .ok_or(LibError::NotEnoughEnergy)?;
+        self.discover_line(robot, world, length, width, direction)
+    }
 
-                let mut tiles: Vec<(usize, usize)> = vec![];
-                for y in inter_y {
-                    for x in inter_x.clone() {
-                        tiles.push((y, x))
-                    }
-                }
+    /// # Performs a line discovery choosing the border behaviour
+    /// Same as `discover_line`, but the caller decides what happens to the part of the strip that falls
+    /// outside of the map: with `BorderMode::Clamp` it is ignored (like `discover_line` does), with
+    /// `BorderMode::Wrap` it continues from the opposite edge of the map.
+    ///
+    /// # Returns
+    /// - The number of discovered tiles or an error.
+    pub fn discover_line_with_border(
+        &mut self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        length: usize,
+        width: usize,
+        direction: Direction,
+        border: BorderMode,
+    ) -> Result<usize, LibError> {
+        let to_visit = self.strip_tiles(world, length, width, &direction, border);
+        self.discover_all(robot, world, to_visit, false)
+    }
 
-                tiles
-            }
-            | Direction::Right => {
-                let inter_y;
-                let inter_x;
-
-                if self.coordinates.get_row() < width / 2 {
-                    inter_y = (0..=self.coordinates.get_row() + width / 2).rev();
-                } else {
-                    if self.coordinates.get_row() + width / 2 >= world_dim {
-                        inter_y = (self.coordinates.get_row() - width / 2..=world_dim - 1).rev();
-                    } else {
-                        inter_y =
-                            (self.coordinates.get_row() - width / 2..=self.coordinates.get_row() + width / 2).rev();
-                    }
-                }
+    /// # Estimates the energy of a line discovery
+    /// Returns the energy `discover_line` would spend with the same parameters: 3 for every tile of the
+    /// strip that is not yet present in the robots personal map.
+    pub fn estimate_energy(&self, world: &World, length: usize, width: usize, direction: Direction) -> usize {
+        let map = match robot_map(world) {
+            | None => return 0,
+            | Some(map) => map,
+        };
+        let unknown = self
+            .line_tiles(world, length, width, &direction)
+            .iter()
+            .filter(|(row, col)| map[*row][*col].is_none())
+            .count();
+        unknown * DISCOVERY_COST
+    }
 
-                if (self.coordinates.get_col() + length as usize - 1usize) >= robot_map(world).unwrap().len() {
-                    inter_x = self.coordinates.get_col()..=robot_map(world).unwrap().len() - 1
-                } else {
-                    inter_x = self.coordinates.get_col()..=self.coordinates.get_col() + length as usize - 1usize
-                }
+    /// # Estimates the energy of a line discovery in every direction
+    /// Same as `estimate_energy`, for each of the four directions from the current position of the bot,
+    /// to pick the cheapest one (or the one that discovers the most, since the cost grows with the unknown tiles).
+    pub fn estimate_all_directions(&self, world: &World, length: usize, width: usize) -> HashMap<Direction, usize> {
+        [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+            .into_iter()
+            .map(|d| (d.clone(), self.estimate_energy(world, length, width, d)))
+            .collect()
+    }
+
+    /// Computes the coordinates of the strip of tiles covered by a line discovery, clipped to the map.
+    pub(crate) fn line_tiles(
+        &self,
+        world: &World,
+        length: usize,
+        width: usize,
+        direction: &Direction,
+    ) -> Vec<(usize, usize)> {
+        self.strip_tiles(world, length, width, direction, BorderMode::Clamp)
+    }
 
-                let mut tiles: Vec<(usize, usize)> = vec![];
-                for x in inter_x {
-                    for y in inter_y.clone() {
-                        tiles.push((y, x))
+    /// Computes the coordinates of the strip of tiles covered by a line discovery: `length` tiles along the
+    /// direction (starting from the bot) times `width / 2` tiles on each side, handling the borders as requested.
+    pub(crate) fn strip_tiles(
+        &self,
+        world: &World,
+        length: usize,
+        width: usize,
+        direction: &Direction,
+        border: BorderMode,
+    ) -> Vec<(usize, usize)> {
+        let world_dim = robot_map(world).map_or(0, |map| map.len());
+        Self::strip(self.coordinates, world_dim, length, width, direction, border)
+    }
+
+    /// Same as `strip_tiles`, for a strip starting from `origin` on a map of side `world_dim`.
+    pub(crate) fn strip(
+        origin: ChartedCoordinate,
+        world_dim: usize,
+        length: usize,
+        width: usize,
+        direction: &Direction,
+        border: BorderMode,
+    ) -> Vec<(usize, usize)> {
+        let (row, col) = (origin.get_row() as i64, origin.get_col() as i64);
+        let half = (width / 2) as i64;
+        // the strip is walked from the bot outward, and across it in the same order as the original version
+        let (step, across): ((i64, i64), Vec<i64>) = match direction {
+            | Direction::Up => ((-1, 0), (-half..=half).collect()),
+            | Direction::Down => ((1, 0), (-half..=half).collect()),
+            | Direction::Right => ((0, 1), (-half..=half).rev().collect()),
+            | Direction::Left => ((0, -1), (-half..=half).rev().collect()),
+        };
+
+        let mut tiles: Vec<(usize, usize)> = vec![];
+        for k in 0..length as i64 {
+            for offset in across.iter() {
+                let (r, c) = match direction {
+                    | Direction::Up | Direction::Down => (row + step.0 * k, col + offset),
+                    | Direction::Left | Direction::Right => (row + offset, col + step.1 * k),
+                };
+                if let (Some(r), Some(c)) = (border.apply(r, world_dim), border.apply(c, world_dim)) {
+                    if !tiles.contains(&(r, c)) {
+                        tiles.push((r, c));
                     }
                 }
-                tiles
             }
-            | Direction::Left => {
-                let int_y;
-                let int_x;
-
-                if self.coordinates.get_row() < width / 2 {
-                    int_y = (0..=self.coordinates.get_row() + width / 2).rev();
-                } else {
-                    if self.coordinates.get_row() + width / 2 >= world_dim {
-                        int_y = (self.coordinates.get_row() - width / 2..=world_dim).rev();
-                    } else {
-                        int_y = (self.coordinates.get_row() - width / 2..=self.coordinates.get_row() + width / 2).rev();
-                    }
-                }
+        }
+        tiles
+    }
 
-                if (self.coordinates.get_col() as i32 - length as i32) < 0 {
-                    int_x = (0..=self.coordinates.get_col()).rev();
-                } else {
-                    int_x = (self.coordinates.get_col() - length as usize + 1..=self.coordinates.get_col()).rev();
-                }
+    /// # Performs a discovery toward a coordinate
+    /// Discovers the strip of tiles along the straight line (computed with Bresenham's algorithm) that goes
+    /// from the bot to the target, instead of following a compass direction.
+    ///
+    /// # Parameters
+    /// - robot: A mutable reference to the robot whose personal map has to be discovered.
+    /// - world: A mutable reference to the world.
+    /// - target: The coordinate the strip heads to (included).
+    /// - width: The width of the strip of tiles to discovered (should an odd number).
+    ///
+    /// # Errors
+    /// This function will return an error if during the discovery of the maps,
+    /// the maximum ammount of discoverable tiles is reached  (`LibError::NoMoreDiscoverable`) or the robot does
+    /// not have enough energy to complete the discovery (`LibError::NotEnoughEnergy`).
+    ///
+    /// # Returns
+    /// - The number of discovered tiles or an error.
+    ///
+    /// ## Notes
+    /// - Tiles that are already present in the robots map will not be checked.
+    /// - Tiles are discovered from the bot toward the target, so if the energy runs out the closest ones are known.
+    /// - The part of the strip outside of the map is ignored.
+    pub fn discover_toward(
+        &mut self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        target: ChartedCoordinate,
+        width: usize,
+    ) -> Result<usize, LibError> {
+        let world_dim = robot_map(world).map_or(0, |map| map.len());
+        let half = width / 2;
+        let mut to_visit: Vec<(usize, usize)> = vec![];
 
-                let mut tiles: Vec<(usize, usize)> = vec![];
-                for x in int_x {
-                    for y in int_y.clone() {
-                        tiles.push((y, x))
+        for c in ChartedCoordinate::bresenham_line(&self.coordinates, &target) {
+            for row in c.0.saturating_sub(half)..=c.0 + half {
+                for col in c.1.saturating_sub(half)..=c.1 + half {
+                    if row < world_dim && col < world_dim && !to_visit.contains(&(row, col)) {
+                        to_visit.push((row, col));
                     }
                 }
-
-                tiles
             }
-        };
+        }
+        self.discover_all(robot, world, to_visit, false)
+    }
 
+    /// Discovers the given tiles one at a time, skipping the ones already in the robots map unless forced,
+    /// until the cap set with `set_max_tiles` is reached.
+    pub(crate) fn discover_all(
+        &self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        to_visit: Vec<(usize, usize)>,
+        force: bool,
+    ) -> Result<usize, LibError> {
         let mut discovered: usize = 0;
 
         for t in to_visit {
+            if self.max_tiles.map_or(false, |max| discovered >= max) {
+                break;
+            }
             match Self::check_discovered(world, t) {
+                | Ok(true) if !force => {}
                 | Ok(_) => match discover_tiles(robot, world, &[t]) {
                     | Ok(_) => discovered += 1,
                     | Err(e) => return Err(e),
@@ -204,11 +381,65 @@ impl ChartingBot {
         Ok(discovered)
     }
 
+    /// # Discovers tiles with retries
+    /// Discovers the given tiles one at a time, trying again a tile whose discovery failed up to the number of
+    /// times set with `set_retries`, so that a transient error of `discover_tiles` does not lose the tile.
+    ///
+    /// # Parameters
+    /// - robot: A mutable reference to the robot whose personal map has to be discovered.
+    /// - world: A mutable reference to the world.
+    /// - to_visit: The tiles to be discovered, in order.
+    ///
+    /// # Returns
+    /// - A `DiscoveryReport` with the tiles that succeeded and the ones that failed.
+    ///
+    /// ## Notes
+    /// - Tiles that are already present in the robots map will not be checked, they are reported as succeeded.
+    /// - When a tile still fails after all its retries the sweep gives up: that tile and all the ones after it
+    /// are reported as failed, together with the last error.
+    /// - Running out of energy (`LibError::NotEnoughEnergy`) or of discoverable tiles (`LibError::NoMoreDiscovery`)
+    /// cannot be fixed by trying again, so they make the sweep give up without retrying.
+    pub fn discover_tiles_retrying(
+        &mut self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        to_visit: &[ChartedCoordinate],
+    ) -> DiscoveryReport {
+        let mut report = DiscoveryReport::default();
+
+        for (i, c) in to_visit.iter().enumerate() {
+            let t = (c.get_row(), c.get_col());
+            let mut attempts: usize = 0;
+            let outcome = loop {
+                let result = match Self::check_discovered(world, t) {
+                    | Ok(true) => Ok(()),
+                    | Ok(false) => discover_tiles(robot, world, &[t]).map(|_| ()),
+                    | Err(e) => Err(e),
+                };
+                match result {
+                    | Err(LibError::NotEnoughEnergy) => break Err(LibError::NotEnoughEnergy),
+                    | Err(LibError::NoMoreDiscovery) => break Err(LibError::NoMoreDiscovery),
+                    | Err(_) if attempts < self.retries => attempts += 1,
+                    | other => break other,
+                }
+            };
+            match outcome {
+                | Ok(()) => report.succeeded.push(*c),
+                | Err(e) => {
+                    report.failed.extend_from_slice(&to_visit[i..]);
+                    report.error = Some(e);
+                    break;
+                }
+            }
+        }
+        report
+    }
+
     /// Checks if a tile in a given coordinale is already present in the robots personal map.
     pub(crate) fn check_discovered(world: &World, coordinate: (usize, usize)) -> Result<bool, LibError> {
         if coordinate.0 < robot_map(world).unwrap().len() && coordinate.1 < robot_map(world).unwrap()[0].len() {
             match &robot_map(world).unwrap()[coordinate.0][coordinate.1] {
-                | Some(_) => Ok(false),
+                | Some(_) => Ok(true),
                 | None => Ok(false),
             }
         } else {
@@ -229,6 +460,8 @@ impl ChartingBot {
     /// This function will return an error if during the discovery of the maps,
     /// the maximum ammount of discoverable tiles is reached  (`LibError::NoMoreDiscoverable`) or the robot does
     /// not have enough energy to complete the discovery (`LibError::NotEnoughEnergy`).
+    /// Together with the error it returns how many directions of the path were completed, so that the
+    /// discovery can be continued later with `resume_path`.
     ///
     /// # Returns
     /// - The number of discovered tiles or an error.
@@ -237,6 +470,9 @@ impl ChartingBot {
     /// - Tiles that are already present in the robots map will not be checked.
     /// - Using an even number for the width value will result in a strip as wide as the next odd
     /// number to one inserted.
+    /// - On error the bot is left on the last step completed successfully.
+    /// - A direction that would take the bot outside of the map stops the discovery with `LibError::OutOfBounds`,
+    /// the number returned with it is the index of that direction in the path.
 
     pub fn discover_path(
         &mut self,
@@ -244,18 +480,259 @@ impl ChartingBot {
         world: &mut World,
         width: usize,
         path: Vec<Direction>,
-    ) -> Result<usize, LibError> {
+    ) -> Result<usize, (LibError, usize)> {
+        let world_dim = robot_map(world).map_or(0, |map| map.len());
         let mut discovered: usize = 0;
         for d in path {
+            if !self.can_move(&d, world_dim) {
+                return Err((LibError::OutOfBounds, discovered));
+            }
             Self::move_bot(self, &d);
-            match Self::discover_line(self, robot, world, 1, width, d) {
+            match Self::discover_line(self, robot, world, 1, width, d.clone()) {
+                | Ok(_) => discovered += 1,
+                | Err(e) => {
+                    // the step failed, the bot goes back where the last completed one left it
+                    Self::move_bot(self, &Self::opposite(&d));
+                    return Err((e, discovered));
+                }
+            }
+        }
+        Ok(discovered)
+    }
+
+    /// # Resumes a path discovery
+    /// Continues a `discover_path` that stopped with an error, starting from the direction at index
+    /// `from_step` (the number of completed directions returned with the error).
+    ///
+    /// # Errors
+    /// The same errors of `discover_path`; the number of completed directions returned with them counts from
+    /// the start of the whole path, so it can be passed again to `resume_path`.
+    ///
+    /// # Returns
+    /// - The number of discovered tiles or an error.
+    ///
+    /// ## Notes
+    /// - The bot has to be where the failed discovery left it, so it must not be moved in between.
+    /// - A `from_step` past the end of the path discovers nothing.
+    pub fn resume_path(
+        &mut self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        width: usize,
+        path: Vec<Direction>,
+        from_step: usize,
+    ) -> Result<usize, (LibError, usize)> {
+        let remaining: Vec<Direction> = path.into_iter().skip(from_step).collect();
+        self.discover_path(robot, world, width, remaining)
+            .map_err(|(e, completed)| (e, from_step + completed))
+    }
+
+    /// # Discovers and plans a path
+    /// Convenience for the common "scout then route" pattern: performs a line discovery (as `discover_line`),
+    /// initializes the given ChartedPaths with the updated robots personal map and plans the path from the
+    /// robot to the target (as `ChartedPaths::shortest_path`).
+    ///
+    /// # Parameters
+    /// - robot: A mutable reference to the robot whose personal map has to be discovered.
+    /// - world: A mutable reference to the world.
+    /// - paths: A ChartedPaths that has not been initialized yet, it will be used for the planning.
+    /// - length, width, direction: The strip to be discovered, as in `discover_line`.
+    /// - target: The coordinate the path has to reach.
+    ///
+    /// # Errors
+    /// The same errors of `discover_line`; in that case the ChartedPaths is not initialized.
+    ///
+    /// # Returns
+    /// - The number of discovered tiles and the planned path, None if the target cannot be reached, or an error.
+    #[allow(clippy::too_many_arguments)]
+    pub fn discover_and_plan(
+        &mut self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        paths: &mut ChartedPaths,
+        length: usize,
+        width: usize,
+        direction: Direction,
+        target: ChartedCoordinate,
+    ) -> Result<(usize, Option<Vec<ChartedCoordinate>>), LibError> {
+        let discovered = self.discover_line(robot, world, length, width, direction)?;
+        let map = robot_map(world).ok_or(LibError::OutOfBounds)?;
+        paths.init(&map, world);
+        let path = paths
+            .shortest_path(ChartedCoordinate::from(robot.get_coordinate()), target)
+            .map(|(_, path)| path);
+        Ok((discovered, path))
+    }
+
+    /// # Performs a coverage discovery
+    /// Discovers the map in rings of growing radius around the bot (like a square spiral), until the
+    /// given fraction of the map is present in the robots personal map.
+    ///
+    /// # Parameters
+    /// - robot: A mutable reference to the robot whose personal map has to be discovered.
+    /// - world: A mutable reference to the world.
+    /// - target_fraction: The fraction of the map (between 0 and 1) that should be known at the end.
+    ///
+    /// # Errors
+    /// Running out of energy (`LibError::NotEnoughEnergy`) or of discoverable tiles (`LibError::NoMoreDiscovery`)
+    /// simply stops the discovery early, any other error of `discover_tiles` is returned.
+    ///
+    /// # Returns
+    /// - The number of discovered tiles or an error.
+    ///
+    /// ## Notes
+    /// - Tiles that are already present in the robots map will not be checked.
+    /// - The discovery also stops when the whole map has been swept, even if the target was not reached.
+    pub fn discover_until_coverage(
+        &mut self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        target_fraction: f64,
+    ) -> Result<usize, LibError> {
+        let world_dim = robot_map(world).map_or(0, |map| map.len());
+        let mut discovered: usize = 0;
+        let center = (self.coordinates.get_row() as i64, self.coordinates.get_col() as i64);
+
+        for radius in 0..world_dim as i64 {
+            if Self::coverage(world) >= target_fraction {
+                break;
+            }
+            // every tile whose distance (in rings) from the bot is exactly radius
+            for row in center.0 - radius..=center.0 + radius {
+                for col in center.1 - radius..=center.1 + radius {
+                    if (row - center.0).abs() != radius && (col - center.1).abs() != radius {
+                        continue;
+                    }
+                    if row < 0 || col < 0 || row >= world_dim as i64 || col >= world_dim as i64 {
+                        continue;
+                    }
+                    let t = (row as usize, col as usize);
+                    if robot_map(world).map_or(true, |map| map[t.0][t.1].is_some()) {
+                        continue;
+                    }
+                    match discover_tiles(robot, world, &[t]) {
+                        | Ok(_) => discovered += 1,
+                        | Err(LibError::NotEnoughEnergy) | Err(LibError::NoMoreDiscovery) => return Ok(discovered),
+                        | Err(e) => return Err(e),
+                    }
+                    if Self::coverage(world) >= target_fraction {
+                        return Ok(discovered);
+                    }
+                }
+            }
+        }
+        Ok(discovered)
+    }
+
+    /// # Performs a prioritized discovery
+    /// Discovers, one at a time, the frontier tiles (unknown tiles next to a known one) that reveal the most:
+    /// the next tile is always the one with the most unknown neighbours, so every unit of energy
+    /// uncovers as much of the unexplored area as possible.
+    ///
+    /// # Parameters
+    /// - robot: A mutable reference to the robot whose personal map has to be discovered.
+    /// - world: A mutable reference to the world.
+    /// - budget: The maximum amount of energy that can be spent (every discovered tile costs 3 energy).
+    ///
+    /// # Errors
+    /// Running out of energy (`LibError::NotEnoughEnergy`) or of discoverable tiles (`LibError::NoMoreDiscovery`)
+    /// simply stops the discovery early, any other error of `discover_tiles` is returned.
+    ///
+    /// # Returns
+    /// - The number of discovered tiles or an error.
+    ///
+    /// ## Notes
+    /// - The discovery also stops when there are no frontier tiles left.
+    /// - On equal scores the tile with the smallest coordinate (by row, then by column) is discovered first.
+    pub fn discover_prioritized(
+        &mut self,
+        robot: &mut impl Runnable,
+        world: &mut World,
+        budget: usize,
+    ) -> Result<usize, LibError> {
+        let mut discovered: usize = 0;
+        let mut spent: usize = 0;
+
+        while spent + DISCOVERY_COST <= budget {
+            let map = match robot_map(world) {
+                | None => break,
+                | Some(map) => map,
+            };
+            let best = Self::frontier(&map)
+                .into_iter()
+                .max_by_key(|(t, score)| (*score, usize::MAX - t.0, usize::MAX - t.1));
+            let next = match best {
+                | None => break,
+                | Some((t, _)) => t,
+            };
+            match discover_tiles(robot, world, &[next]) {
                 | Ok(_) => discovered += 1,
+                | Err(LibError::NotEnoughEnergy) | Err(LibError::NoMoreDiscovery) => return Ok(discovered),
                 | Err(e) => return Err(e),
             }
+            spent += DISCOVERY_COST;
         }
         Ok(discovered)
     }
 
+    /// Unknown tiles next to at least one known tile, each with the number of its unknown neighbours.
+    pub(crate) fn frontier(map: &Vec<Vec<Option<Tile>>>) -> Vec<((usize, usize), usize)> {
+        let dim = map.len();
+        let mut frontier = vec![];
+        for (i, row) in map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                if tile.is_some() {
+                    continue;
+                }
+                let neighbours: Vec<(usize, usize)> = [(-1i64, 0i64), (1, 0), (0, -1), (0, 1)]
+                    .iter()
+                    .map(|(dr, dc)| (i as i64 + dr, j as i64 + dc))
+                    .filter(|(r, c)| *r >= 0 && *c >= 0 && (*r as usize) < dim)
+                    .filter(|(r, c)| (*c as usize) < map[*r as usize].len())
+                    .map(|(r, c)| (r as usize, c as usize))
+                    .collect();
+                let unknown = neighbours.iter().filter(|(r, c)| map[*r][*c].is_none()).count();
+                if unknown < neighbours.len() {
+                    frontier.push(((i, j), unknown));
+                }
+            }
+        }
+        frontier
+    }
+
+    /// Fraction of the map currently present in the robots personal map.
+    pub(crate) fn coverage(world: &World) -> f64 {
+        match robot_map(world) {
+            | None => 0.0,
+            | Some(map) => {
+                let total: usize = map.iter().map(|row| row.len()).sum();
+                if total == 0 {
+                    return 1.0;
+                }
+                let known = map.iter().flatten().filter(|tile| tile.is_some()).count();
+                known as f64 / total as f64
+            }
+        }
+    }
+
+    fn can_move(&self, direction: &Direction, dim: usize) -> bool {
+        match direction {
+            | Direction::Up => self.coordinates.0 > 0,
+            | Direction::Down => self.coordinates.0 + 1 < dim,
+            | Direction::Left => self.coordinates.1 > 0,
+            | Direction::Right => self.coordinates.1 + 1 < dim,
+        }
+    }
+
+    fn opposite(direction: &Direction) -> Direction {
+        match direction {
+            | Direction::Up => Direction::Down,
+            | Direction::Down => Direction::Up,
+            | Direction::Left => Direction::Right,
+            | Direction::Right => Direction::Left,
+        }
+    }
+
     //Alters the position of the carting bot given the movements direction.
     pub(crate) fn move_bot(&mut self, direction: &Direction) {
         match direction {
@@ -264,6 +741,49 @@ impl ChartingBot {
             | Direction::Left => self.coordinates.1 -= 1,
             | Direction::Right => self.coordinates.1 += 1,
         }
-        // println!("DiscoveryBot moved to: {:?}", self.coordinates)
+        #[cfg(feature = "log")]
+        log::debug!("ChartingBot moved to ({})", self.coordinates);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{uncounted, with_world};
+
+    #[test]
+    fn discover_path_stops_at_the_top_left_corner() {
+        with_world(|robot, world| {
+            for path in [vec![Direction::Up], vec![Direction::Left], vec![Direction::Left, Direction::Down]] {
+                let mut bot = uncounted::<ChartingBot>();
+                bot.init(&*robot);
+                assert_eq!(bot.position(), ChartedCoordinate(0, 0));
+                let result = bot.discover_path(robot, world, 3, path);
+                assert!(matches!(result, Err((LibError::OutOfBounds, 0))));
+                assert_eq!(bot.position(), ChartedCoordinate(0, 0));
+            }
+        });
+    }
+
+    #[test]
+    fn strips_stay_inside_the_map() {
+        let dim = 5;
+        // a tile on each edge, corners included, and one in the middle
+        let origins = [(0, 0), (0, 2), (0, 4), (2, 0), (2, 2), (2, 4), (4, 0), (4, 2), (4, 4)];
+        for border in [BorderMode::Clamp, BorderMode::Wrap] {
+            for value in -12..12 {
+                assert!(border.apply(value, dim).map_or(true, |v| v < dim));
+            }
+            for (row, col) in origins {
+                for direction in [Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+                    let tiles = ChartingBot::strip(ChartedCoordinate(row, col), dim, 7, 5, &direction, border);
+                    assert!(tiles.contains(&(row, col)));
+                    assert!(tiles.iter().all(|(r, c)| *r < dim && *c < dim), "{border:?} {direction:?} {tiles:?}");
+                }
+            }
+        }
+        assert_eq!(BorderMode::Clamp.apply(-1, dim), None);
+        assert_eq!(BorderMode::Wrap.apply(-1, dim), Some(4));
+        assert_eq!(BorderMode::Wrap.apply(5, dim), Some(0));
     }
 }
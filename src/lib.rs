@@ -1,4 +1,5 @@
 use std::fmt::Debug;
+use std::ops::{Deref, DerefMut};
 use std::sync::Mutex;
 
 use robotics_lib::interface::Tools;
@@ -8,6 +9,8 @@ pub mod charted_map;
 pub mod charted_paths;
 pub mod charted_world;
 pub mod charting_bot;
+#[cfg(test)]
+mod test_utils;
 
 const LIMIT: u8 = 3;
 lazy_static::lazy_static! {
@@ -22,7 +25,7 @@ lazy_static::lazy_static! {
 ///
 /// - ChartedWorld
 ///
-///     editable vec-based map of discovered tiles
+///     editable vec-based map of discovered tiles (or SparseChartedWorld, storing only the discovered ones)
 /// - ChartedMap
 ///
 ///     used to save points of interest and retrieve them later
@@ -56,6 +59,59 @@ lazy_static::lazy_static! {
 /// ```
 pub struct ChartingTools;
 
+/// # Enum: ToolError
+/// reason why one or more tools could not be obtained
+/// - TooManyTools(n): the request would exceed the limit, `n` tools are currently active
+/// - Unavailable: the tool counter could not be accessed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolError {
+    TooManyTools(u8),
+    Unavailable,
+}
+
+/// # Struct: ToolHandle
+/// a ChartingTool obtained via `ChartingTools::acquire`, usable exactly like the tool itself.
+///
+/// the slot taken by the tool is freed as soon as the handle is dropped, which happens at the end of the
+/// scope it was declared in: keeping the handle in a small block makes it clear how long the slot stays taken.
+/// `into_inner` gives back the raw tool, whose own drop will then free the slot
+///
+/// ## Example
+/// ```
+/// use charting_tools::charted_world::ChartedWorld;
+/// use charting_tools::ChartingTools;
+///
+/// {
+///     let world = ChartingTools::acquire::<ChartedWorld>().unwrap();
+///     let map = world.get_map();
+/// } // the slot is free again here
+/// ```
+#[derive(Debug)]
+pub struct ToolHandle<T: ChartingTool> {
+    tool: T,
+}
+
+impl<T: ChartingTool> ToolHandle<T> {
+    /// returns the wrapped tool, which keeps the slot until it is dropped
+    pub fn into_inner(self) -> T {
+        self.tool
+    }
+}
+
+impl<T: ChartingTool> Deref for ToolHandle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tool
+    }
+}
+
+impl<T: ChartingTool> DerefMut for ToolHandle<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tool
+    }
+}
+
 impl Tools for ChartingTools {}
 
 impl ChartingTools {
@@ -87,6 +143,135 @@ impl ChartingTools {
             Err(0)
         }
     }
+
+    /// # Constructor
+    /// same as `tool`, but the tool is wrapped in a `ToolHandle`, which frees its slot when dropped
+    pub fn acquire<T: ChartingTool>() -> Result<ToolHandle<T>, u8> {
+        ChartingTools::tool::<T>().map(|tool| ToolHandle { tool })
+    }
+
+    /// # Constructor
+    /// same as `tool`, but it returns two tools at once: either both are obtained or none is,
+    /// so a failure never leaves one tool allocated and the other missing
+    ///
+    /// ## Example
+    /// ```
+    /// use charting_tools::charted_paths::ChartedPaths;
+    /// use charting_tools::charted_world::ChartedWorld;
+    /// use charting_tools::ChartingTools;
+    ///
+    /// let result = ChartingTools::tools::<ChartedWorld, ChartedPaths>();
+    /// match result {
+    ///     Ok((world, paths)) => println!("got both tools"),
+    ///     Err(err) => eprintln!("could not get the tools: {err:?}")
+    /// }
+    /// ```
+    pub fn tools<A: ChartingTool, B: ChartingTool>() -> Result<(A, B), ToolError> {
+        ChartingTools::reserve(2)?;
+        Ok((A::new(), B::new()))
+    }
+
+    /// # Constructor
+    /// same as `tools`, but for three tools at once
+    pub fn tools3<A: ChartingTool, B: ChartingTool, C: ChartingTool>() -> Result<(A, B, C), ToolError> {
+        ChartingTools::reserve(3)?;
+        Ok((A::new(), B::new(), C::new()))
+    }
+
+    /// # Testing aid
+    /// sets the number of active tools back to 0.
+    ///
+    /// the counter is global to the whole process, so tools that leak or are still alive at the end of a test
+    /// are counted against the following ones: calling this in the setup or teardown of each test keeps them
+    /// isolated from each other.
+    ///
+    /// it is only available with the `testing` feature, so that it cannot be used to silently get around the
+    /// limit in a real run; enable the feature only for the crate's dev-dependencies.
+    ///
+    /// **NB**: tools still alive when it is called free a slot anyway when dropped, so the count ends up lower than
+    /// the actual number of active tools: drop everything before resetting
+    #[cfg(feature = "testing")]
+    pub fn reset_counter() {
+        if let Ok(mut n) = NUMBER.lock() {
+            *n = 0;
+        }
+    }
+
+    /// returns the number of tools currently active, the ones obtained through ChartingTools and not yet dropped.
+    /// clones of a tool are not counted, since they do not take a slot
+    ///
+    /// ## Example
+    /// ```
+    /// use charting_tools::charted_world::ChartedWorld;
+    /// use charting_tools::ChartingTools;
+    ///
+    /// let world = ChartingTools::tool::<ChartedWorld>().unwrap();
+    /// let before = ChartingTools::active_count();
+    /// drop(world.clone());
+    /// assert_eq!(ChartingTools::active_count(), before);
+    /// ```
+    pub fn active_count() -> u8 {
+        NUMBER.lock().map_or(0, |n| *n)
+    }
+
+    fn reserve(amount: u8) -> Result<(), ToolError> {
+        if let Ok(mut n) = NUMBER.lock() {
+            if *n + amount <= LIMIT {
+                *n = *n + amount;
+                Ok(())
+            } else {
+                Err(ToolError::TooManyTools(*n))
+            }
+        } else {
+            Err(ToolError::Unavailable)
+        }
+    }
+}
+
+/// the slot a tool takes among the active ones: only a tool obtained through ChartingTools holds a counted slot,
+/// which is freed once, when the tool is dropped. Cloning a tool gives an uncounted slot, so that dropping
+/// the copy does not free the slot of the original
+#[derive(Debug)]
+pub(crate) struct Slot {
+    counted: bool,
+}
+
+impl Slot {
+    /// the slot of a tool created by ChartingTools, after the counter has been incremented
+    pub(crate) fn acquired() -> Self {
+        Slot { counted: true }
+    }
+
+    /// a slot that does not count toward the limit
+    pub(crate) fn uncounted() -> Self {
+        Slot { counted: false }
+    }
+
+    /// frees the slot, if it was counted; further calls do nothing
+    pub(crate) fn release(&mut self) {
+        if !self.counted {
+            return;
+        }
+        self.counted = false;
+        if let Ok(mut n) = NUMBER.lock() {
+            if *n > 0 {
+                *n = *n - 1;
+            }
+        }
+    }
+}
+
+impl Clone for Slot {
+    fn clone(&self) -> Self {
+        Slot::uncounted()
+    }
+}
+
+impl PartialEq for Slot {
+    // whether a slot is counted says nothing about the contents of the tool
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
 }
 
 #[allow(drop_bounds)]
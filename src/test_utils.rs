@@ -0,0 +1,123 @@
+//! helpers shared by the unit tests of the crate
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::mem::ManuallyDrop;
+use std::rc::Rc;
+
+use robotics_lib::energy::Energy;
+use robotics_lib::event::events::Event;
+use robotics_lib::runner::backpack::BackPack;
+use robotics_lib::runner::{Robot, Runnable, Runner};
+use robotics_lib::world::coordinates::Coordinate;
+use robotics_lib::world::environmental_conditions::{EnvironmentalConditions, WeatherType};
+use robotics_lib::world::tile::{Content, Tile, TileType};
+use robotics_lib::world::world_generator::Generator;
+use robotics_lib::world::World;
+
+use crate::reserved::New;
+
+/// side of the world built by `with_world`
+pub(crate) const WORLD_SIDE: usize = 5;
+
+/// a fresh tool that does not take a slot, so that the tests running in parallel never hit the limit.
+/// The tool built by `new` holds a counted slot that was never added to the counter, so it must not be
+/// dropped: it is left alone and its clone, which is uncounted, is returned. A fresh tool owns no memory
+pub(crate) fn uncounted<T: New + Clone>() -> T {
+    let tool = ManuallyDrop::new(T::new());
+    (*tool).clone()
+}
+
+/// builds a map from rows of characters, using the same characters of `ChartedWorld::to_ascii`:
+/// a space is an undiscovered tile, every tile has no content and elevation 0
+pub(crate) fn grid(rows: &[&str]) -> Vec<Vec<Option<Tile>>> {
+    rows.iter().map(|row| row.chars().map(tile_of).collect()).collect()
+}
+
+/// a tile of the given type, without content and at elevation 0
+pub(crate) fn tile(tile_type: TileType) -> Tile {
+    Tile { tile_type, content: Content::None, elevation: 0 }
+}
+
+fn tile_of(c: char) -> Option<Tile> {
+    let tile_type = match c {
+        | '~' => TileType::DeepWater,
+        | '-' => TileType::ShallowWater,
+        | ':' => TileType::Sand,
+        | '.' => TileType::Grass,
+        | '=' => TileType::Street,
+        | 'n' => TileType::Hill,
+        | '^' => TileType::Mountain,
+        | '*' => TileType::Snow,
+        | '!' => TileType::Lava,
+        | 'T' => TileType::Teleport(true),
+        | '#' => TileType::Wall,
+        | _ => return None,
+    };
+    Some(tile(tile_type))
+}
+
+/// the robot of the test world: at its first tick it runs the check it was given
+pub(crate) struct TestRobot {
+    robot: Robot,
+    check: Option<Box<dyn FnOnce(&mut TestRobot, &mut World)>>,
+    ran: Rc<Cell<bool>>,
+}
+
+impl Runnable for TestRobot {
+    fn process_tick(&mut self, world: &mut World) {
+        if let Some(check) = self.check.take() {
+            check(self, world);
+            self.ran.set(true);
+        }
+    }
+
+    fn handle_event(&mut self, _event: Event) {}
+
+    fn get_energy(&self) -> &Energy {
+        &self.robot.energy
+    }
+
+    fn get_energy_mut(&mut self) -> &mut Energy {
+        &mut self.robot.energy
+    }
+
+    fn get_coordinate(&self) -> &Coordinate {
+        &self.robot.coordinate
+    }
+
+    fn get_coordinate_mut(&mut self) -> &mut Coordinate {
+        &mut self.robot.coordinate
+    }
+
+    fn get_backpack(&self) -> &BackPack {
+        &self.robot.backpack
+    }
+
+    fn get_backpack_mut(&mut self) -> &mut BackPack {
+        &mut self.robot.backpack
+    }
+}
+
+/// generates a sunny world of grass of side `WORLD_SIDE`, with the robot spawned in (0, 0)
+struct TestGenerator;
+
+impl Generator for TestGenerator {
+    fn gen(
+        &mut self,
+    ) -> (Vec<Vec<Tile>>, (usize, usize), EnvironmentalConditions, f32, Option<HashMap<Content, f32>>) {
+        let map = vec![vec![tile(TileType::Grass); WORLD_SIDE]; WORLD_SIDE];
+        let conditions = EnvironmentalConditions::new(&[WeatherType::Sunny], 15, 12).unwrap();
+        (map, (0, 0), conditions, 100.0, None)
+    }
+}
+
+/// runs the check inside the first tick of a test world, where the robot and the world are available
+/// like in the `process_tick` of a real robot. It fails if the check was never run
+pub(crate) fn with_world(check: impl FnOnce(&mut TestRobot, &mut World) + 'static) {
+    let ran = Rc::new(Cell::new(false));
+    let robot = TestRobot { robot: Robot::new(), check: Some(Box::new(check)), ran: Rc::clone(&ran) };
+    let mut runner = Runner::new(Box::new(robot), &mut TestGenerator).unwrap();
+    runner.game_tick().unwrap();
+    assert!(ran.get(), "the check was not run");
+}